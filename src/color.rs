@@ -0,0 +1,101 @@
+//! Color constants and functions that paint grayscale maps.
+
+use crate::error::require;
+use crate::ops::{linear_scale_noise, normalize, overlay, scale_noise, threshhold};
+use crate::{Buf, Result};
+
+/// Pure black, also what unset pixels are.
+pub const BLACK: [u8; 3] = [0, 0, 0];
+/// Pure white, the "on" color of masks.
+pub const WHITE: [u8; 3] = [255, 255, 255];
+/// Pure blue.
+pub const BLUE: [u8; 3] = [0, 0, 255];
+/// Pure green.
+pub const GREEN: [u8; 3] = [0, 255, 0];
+/// Pure red.
+pub const RED: [u8; 3] = [255, 0, 0];
+/// Shallowest of the sea blues.
+pub const SEA_BLUE1: [u8; 3] = [0, 131, 255];
+/// Second sea blue.
+pub const SEA_BLUE2: [u8; 3] = [0, 107, 209];
+/// Middle sea blue.
+pub const SEA_BLUE3: [u8; 3] = [0, 81, 158];
+/// Fourth sea blue.
+pub const SEA_BLUE4: [u8; 3] = [0, 61, 120];
+/// Deepest of the sea blues.
+pub const SEA_BLUE5: [u8; 3] = [0, 47, 92];
+
+/// Image of a single color.
+pub fn create_solid_image(width: u32, height: u32, color: [u8; 3]) -> Buf{
+    let mut buffer: Buf = image::ImageBuffer::new(width, height);
+    for (_x, _y, pixel) in buffer.enumerate_pixels_mut() {
+        *pixel = image::Rgb(color);
+    }
+    buffer
+}
+
+/// Tints a grayscale map, full brightness becomes red, green, blue and black stays black.
+pub fn recolor_proportion(input: Buf, mut red: f32, mut green: f32, mut blue: f32) -> Buf {
+    red = 255.0 / red;
+    green = 255.0 / green;
+    blue = 255.0 / blue;
+    let mut buffer: Buf = image::ImageBuffer::new(input.width(), input.height());
+    for (x, y, pixel) in input.enumerate_pixels() {
+        let sub_pixel = buffer.get_pixel_mut(x, y);
+        *sub_pixel = image::Rgb([(pixel[0] as f32 / red) as u8, (pixel[0] as f32 / green) as u8, (pixel[0] as f32 / blue) as u8]);
+    }
+    buffer
+}
+
+/// Paints the white pixels of a mask a solid color, everything else is black.
+pub fn recolor_solid(input: Buf, red: u8, green: u8, blue: u8) -> Buf {
+    let mut buffer: Buf = image::ImageBuffer::new(input.width(), input.height());
+    for (x, y, pixel) in input.enumerate_pixels() {
+        let sub_pixel = buffer.get_pixel_mut(x, y);
+        if pixel[0] == 255 && pixel[1] == 255 && pixel[2] == 255 {
+            *sub_pixel = image::Rgb([red, green, blue]);
+        }
+    }
+    buffer
+}
+
+/// How [`terrain_colors`] brightens the water and land bands.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BandScaling {
+    /// Each band is stretched so its brightest pixel reaches full brightness, which uses the whole range
+    /// for a single map but depends on what else is in it.
+    Normalized,
+    /// Each band is brightened by a fixed amount from the levels, so maps colored separately, like
+    /// neighbouring chunks of a world, agree where they meet.
+    Fixed,
+}
+
+/// The water, land and snow palette of the terrain command for a grayscale height map.
+///
+/// Heights below `water_level` are water and from `mountain_level` up are snow, `scaling` sets how the
+/// water and land shades are spread.
+pub fn terrain_colors(input: &Buf, water_level: u8, mountain_level: u8, scaling: BandScaling) -> Result<Buf> {
+    require(water_level >= 1, || "the water level must be at least 1".to_string())?;
+    require(mountain_level > water_level, || format!("the mountain level must be above the water level of {water_level}"))?;
+    let mut snow: Buf = threshhold(input, mountain_level, 255, true);
+    snow = recolor_proportion(snow, 220.0, 220.0, 220.0);
+
+    let mut land: Buf = threshhold(input, water_level, mountain_level - 1, true);
+    match scaling {
+        BandScaling::Normalized => normalize(&mut land),
+        BandScaling::Fixed => scale_noise(&mut land, 255.0 / (mountain_level - 1) as f32),
+    }
+    land = recolor_proportion(land, 128.0, 128.0, 128.0);
+
+    let mut water: Buf = threshhold(input, 0, water_level - 1, true);
+    linear_scale_noise(&mut water, 32);
+    match scaling {
+        BandScaling::Normalized => normalize(&mut water),
+        BandScaling::Fixed => scale_noise(&mut water, 255.0 / (water_level as f32 - 1.0 + 32.0)),
+    }
+    water = recolor_proportion(water, 1.0, 87.0, 255.0);
+
+    overlay(&mut water, land)?;
+    overlay(&mut water, snow)?;
+    Ok(water)
+}
@@ -0,0 +1,124 @@
+//! Exact Euclidean distance fields of masks, for gradients along coasts and bands a set width from them.
+//!
+//! Distances are in pixels between pixel centers, so these fields aren't 0.0..=1.0 heights like other [`Field`]s.
+//! White pixels are those with a red channel of 255.
+
+use crate::{Buf, Field};
+
+// stands in for infinity in the parabola intersections, far beyond any image but still finite
+const FAR: f64 = 1e20;
+
+// squared distance from every sample to the closest sample at 0.0 along one line, Felzenszwalb and Huttenlocher's
+// lower envelope of parabolas rooted at every sample
+fn squared_distances_1d(samples: &[f64], output: &mut [f64], roots: &mut [usize], bounds: &mut [f64]) {
+    let n = samples.len();
+    let mut k: usize = 0;
+    roots[0] = 0;
+    bounds[0] = -f64::INFINITY;
+    bounds[1] = f64::INFINITY;
+    for q in 1..n {
+        // where the parabola from q overtakes the last one in the envelope
+        let intersection = |root: usize| ((samples[q] + (q * q) as f64) - (samples[root] + (root * root) as f64)) / (2.0 * q as f64 - 2.0 * root as f64);
+        let mut s = intersection(roots[k]);
+        while s <= bounds[k] {
+            k -= 1;
+            s = intersection(roots[k]);
+        }
+        k += 1;
+        roots[k] = q;
+        bounds[k] = s;
+        bounds[k + 1] = f64::INFINITY;
+    }
+    k = 0;
+    for (q, distance) in output.iter_mut().enumerate() {
+        while bounds[k + 1] < q as f64 {
+            k += 1;
+        }
+        let offset = q as f64 - roots[k] as f64;
+        *distance = offset * offset + samples[roots[k]];
+    }
+}
+
+// squared distance from every pixel to the closest pixel where inside is true, columns then rows
+fn squared_distances(width: u32, height: u32, inside: impl Fn(u32, u32) -> bool) -> Vec<f64> {
+    let (width, height) = (width as usize, height as usize);
+    let longest = width.max(height);
+    let mut grid: Vec<f64> = vec![FAR; width * height];
+    for y in 0..height {
+        for x in 0..width {
+            if inside(x as u32, y as u32) {
+                grid[y * width + x] = 0.0;
+            }
+        }
+    }
+    let (mut samples, mut output) = (vec![0.0; longest], vec![0.0; longest]);
+    let (mut roots, mut bounds) = (vec![0; longest], vec![0.0; longest + 1]);
+    for x in 0..width {
+        for y in 0..height {
+            samples[y] = grid[y * width + x];
+        }
+        squared_distances_1d(&samples[..height], &mut output[..height], &mut roots, &mut bounds);
+        for y in 0..height {
+            grid[y * width + x] = output[y];
+        }
+    }
+    for y in 0..height {
+        let row = &mut grid[y * width..(y + 1) * width];
+        samples[..width].copy_from_slice(row);
+        squared_distances_1d(&samples[..width], &mut output[..width], &mut roots, &mut bounds);
+        row.copy_from_slice(&output[..width]);
+    }
+    grid
+}
+
+// far distances come from pixels that never saw a white pixel
+fn to_distance(squared: f64) -> f32 {
+    if squared >= FAR { f32::INFINITY } else { squared.sqrt() as f32 }
+}
+
+/// Distance from every pixel to the closest white pixel of `mask`, 0.0 on white pixels.
+/// Every distance is infinite when there are no white pixels.
+pub fn distance_transform(mask: &Buf) -> Field {
+    let squared = squared_distances(mask.width(), mask.height(), |x, y| mask.get_pixel(x, y)[0] == 255);
+    let mut buffer: Field = image::ImageBuffer::new(mask.width(), mask.height());
+    for (pixel, squared) in buffer.pixels_mut().zip(squared) {
+        *pixel = image::Luma([to_distance(squared)]);
+    }
+    buffer
+}
+
+/// Distance to the edge of the white area of `mask`, negative inside it and positive outside.
+/// Pixels on either side of the edge are -1.0 and 1.0, so the edge itself sits between them.
+pub fn signed_distance_transform(mask: &Buf) -> Field {
+    let white = |x: u32, y: u32| mask.get_pixel(x, y)[0] == 255;
+    let outside = squared_distances(mask.width(), mask.height(), white);
+    let inside = squared_distances(mask.width(), mask.height(), |x, y| !white(x, y));
+    let mut buffer: Field = image::ImageBuffer::new(mask.width(), mask.height());
+    for ((pixel, outside), inside) in buffer.pixels_mut().zip(outside).zip(inside) {
+        *pixel = image::Luma([if outside == 0.0 { -to_distance(inside) } else { to_distance(outside) }]);
+    }
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn distance_transform_matches_brute_force() {
+        let mut rng = StdRng::seed_from_u64(47);
+        let mask: Buf = image::ImageBuffer::from_fn(23, 17, |_, _| image::Rgb(if rng.random_ratio(1, 12) { [255; 3] } else { [0; 3] }));
+        let white: Vec<(u32, u32)> = mask.enumerate_pixels().filter(|(_, _, pixel)| pixel[0] == 255).map(|(x, y, _)| (x, y)).collect();
+        assert!(!white.is_empty());
+        for (x, y, distance) in distance_transform(&mask).enumerate_pixels() {
+            let closest = white
+                .iter()
+                .map(|&(wx, wy)| ((x as f64 - wx as f64).powi(2) + (y as f64 - wy as f64).powi(2)).sqrt())
+                .fold(f64::INFINITY, f64::min);
+            assert!((distance[0] as f64 - closest).abs() < 1e-4, "{x}, {y}: {} instead of {closest}", distance[0]);
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! The error type shared by the whole crate.
+
+use std::fmt;
+use std::path::PathBuf;
+
+/// Everything that can go wrong generating or saving a map.
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing a file failed.
+    Io {
+        /// The file that was being read or written.
+        path: PathBuf,
+        /// What went wrong.
+        source: std::io::Error,
+    },
+    /// Encoding an image failed.
+    Image {
+        /// The file that was being written, empty when encoding in memory.
+        path: PathBuf,
+        /// What went wrong.
+        source: image::ImageError,
+    },
+    /// Two images that have to line up pixel for pixel have different sizes.
+    DimensionMismatch {
+        /// Size of the first image.
+        expected: (u32, u32),
+        /// Size of the image that didn't match it.
+        found: (u32, u32),
+    },
+    /// A size, count or other argument is out of range.
+    InvalidArgument(String),
+    /// A pipeline file couldn't be parsed or evaluated.
+    Pipeline(String),
+}
+
+/// Result with the crate [`Error`].
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "{}: {source}", path.display()),
+            Error::Image { path, source } => write!(f, "{}: {source}", path.display()),
+            Error::DimensionMismatch { expected, found } => {
+                write!(f, "images need the same size, got {}x{} and {}x{}", expected.0, expected.1, found.0, found.1)
+            }
+            Error::InvalidArgument(message) | Error::Pipeline(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Image { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl Error {
+    pub(crate) fn io(path: impl Into<PathBuf>) -> impl FnOnce(std::io::Error) -> Error {
+        let path = path.into();
+        move |source| Error::Io { path, source }
+    }
+
+    pub(crate) fn image(path: impl Into<PathBuf>) -> impl FnOnce(image::ImageError) -> Error {
+        let path = path.into();
+        move |source| Error::Image { path, source }
+    }
+}
+
+// fails with a dimension mismatch unless both images are the same size
+pub(crate) fn same_size(first: (u32, u32), second: (u32, u32)) -> Result<()> {
+    if first != second {
+        return Err(Error::DimensionMismatch { expected: first, found: second });
+    }
+    Ok(())
+}
+
+// fails with an invalid argument unless condition holds
+pub(crate) fn require(condition: bool, message: impl FnOnce() -> String) -> Result<()> {
+    if condition { Ok(()) } else { Err(Error::InvalidArgument(message())) }
+}
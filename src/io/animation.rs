@@ -0,0 +1,56 @@
+//! Animated GIF and APNG export, for watching a map being generated.
+
+use std::fs::File;
+use std::io::BufWriter;
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::error::{EncodingError, ImageFormatHint};
+use image::{Delay, DynamicImage, Frame, ImageError, ImageFormat};
+
+use crate::error::{require, same_size};
+use crate::{Buf, Error, Result};
+
+/// Writes `frames` as an animation that loops forever, showing each frame for `frame_delay` milliseconds.
+/// A .gif name writes a GIF with up to 256 colors per frame, .png and .apng write a full color APNG.
+pub fn save_animation(name: &str, frames: &[Buf], frame_delay: u16) -> Result<()> {
+    require(!frames.is_empty(), || "an animation needs at least one frame".to_string())?;
+    for frame in &frames[1..] {
+        same_size(frames[0].dimensions(), frame.dimensions())?;
+    }
+    let extension = std::path::Path::new(name).extension().map(|extension| extension.to_string_lossy().to_lowercase());
+    match extension.as_deref() {
+        Some("gif") => write_gif(name, frames, frame_delay),
+        Some("png") | Some("apng") => write_apng(name, frames, frame_delay),
+        _ => Err(Error::InvalidArgument(format!("animations are written as .gif, .png or .apng, got '{name}'"))),
+    }
+}
+
+fn write_gif(name: &str, frames: &[Buf], frame_delay: u16) -> Result<()> {
+    let file = File::create(name).map_err(Error::io(name))?;
+    // speed 10 is the quantizer's suggested tradeoff, 1 is slow and 30 blotchy
+    let mut encoder = GifEncoder::new_with_speed(BufWriter::new(file), 10);
+    encoder.set_repeat(Repeat::Infinite).map_err(Error::image(name))?;
+    for frame in frames {
+        let rgba = DynamicImage::ImageRgb8(frame.clone()).to_rgba8();
+        let delay = Delay::from_numer_denom_ms(frame_delay as u32, 1);
+        encoder.encode_frame(Frame::from_parts(rgba, 0, 0, delay)).map_err(Error::image(name))?;
+    }
+    Ok(())
+}
+
+// the image crate only reads apng, so this goes through the png crate it uses underneath
+fn write_apng(name: &str, frames: &[Buf], frame_delay: u16) -> Result<()> {
+    let png_error = |error: png::EncodingError| ImageError::Encoding(EncodingError::new(ImageFormatHint::Exact(ImageFormat::Png), error));
+    let file = File::create(name).map_err(Error::io(name))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), frames[0].width(), frames[0].height());
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    // 0 plays forever
+    encoder.set_animated(frames.len() as u32, 0).map_err(png_error).map_err(Error::image(name))?;
+    encoder.set_frame_delay(frame_delay, 1000).map_err(png_error).map_err(Error::image(name))?;
+    let mut writer = encoder.write_header().map_err(png_error).map_err(Error::image(name))?;
+    for frame in frames {
+        writer.write_image_data(frame.as_raw()).map_err(png_error).map_err(Error::image(name))?;
+    }
+    writer.finish().map_err(png_error).map_err(Error::image(name))
+}
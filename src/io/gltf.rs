@@ -0,0 +1,119 @@
+//! glTF 2.0 export.
+
+use std::fs::File;
+use std::io::{BufWriter, Cursor, Write};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use crate::mesh::Mesh;
+use crate::{Buf, Error, Result};
+
+// builds the gltf json and binary buffer for a textured mesh
+// embed puts everything into data uris for a standalone .gltf, otherwise the buffer is returned for a .glb
+fn gltf_document(mesh: &Mesh, texture: &Buf, embed: bool) -> Result<(String, Vec<u8>)> {
+    let mut png: Vec<u8> = Vec::new();
+    texture.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png).map_err(Error::image(""))?;
+
+    let mut bin: Vec<u8> = Vec::new();
+    let mut views: Vec<(usize, usize)> = Vec::new();
+    let mut push_view = |bin: &mut Vec<u8>, bytes: Vec<u8>| {
+        // every view starts on a 4 byte boundary
+        while !bin.len().is_multiple_of(4) {
+            bin.push(0);
+        }
+        views.push((bin.len(), bytes.len()));
+        bin.extend(bytes);
+    };
+    push_view(&mut bin, mesh.positions.iter().flatten().flat_map(|c| c.to_le_bytes()).collect());
+    push_view(&mut bin, mesh.normals.iter().flatten().flat_map(|c| c.to_le_bytes()).collect());
+    push_view(&mut bin, mesh.uvs.iter().flatten().flat_map(|c| c.to_le_bytes()).collect());
+    push_view(&mut bin, mesh.triangles.iter().flatten().flat_map(|i| i.to_le_bytes()).collect());
+    let image: String = if embed {
+        format!("{{\"uri\":\"data:image/png;base64,{}\"}}", STANDARD.encode(&png))
+    } else {
+        push_view(&mut bin, png);
+        "{\"bufferView\":4,\"mimeType\":\"image/png\"}".to_string()
+    };
+    while !bin.len().is_multiple_of(4) {
+        bin.push(0);
+    }
+
+    let mut min: [f32; 3] = [f32::MAX; 3];
+    let mut max: [f32; 3] = [f32::MIN; 3];
+    for position in &mesh.positions {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(position[axis]);
+            max[axis] = max[axis].max(position[axis]);
+        }
+    }
+    let buffer: String = if embed {
+        format!("{{\"byteLength\":{},\"uri\":\"data:application/octet-stream;base64,{}\"}}", bin.len(), STANDARD.encode(&bin))
+    } else {
+        format!("{{\"byteLength\":{}}}", bin.len())
+    };
+    let buffer_views: Vec<String> = views.iter().enumerate().map(|(i, (offset, length))| {
+        // geometry views get a target, the image view must not have one
+        let target = match i {
+            0..=2 => ",\"target\":34962",
+            3 => ",\"target\":34963",
+            _ => "",
+        };
+        format!("{{\"buffer\":0,\"byteOffset\":{offset},\"byteLength\":{length}{target}}}")
+    }).collect();
+    let vertex_count = mesh.positions.len();
+    let json: String = format!(
+        concat!(
+            "{{\"asset\":{{\"version\":\"2.0\",\"generator\":\"titan\"}},",
+            "\"scene\":0,\"scenes\":[{{\"nodes\":[0]}}],\"nodes\":[{{\"mesh\":0,\"name\":\"terrain\"}}],",
+            "\"meshes\":[{{\"primitives\":[{{\"attributes\":{{\"POSITION\":0,\"NORMAL\":1,\"TEXCOORD_0\":2}},\"indices\":3,\"material\":0}}]}}],",
+            "\"materials\":[{{\"pbrMetallicRoughness\":{{\"baseColorTexture\":{{\"index\":0}},\"metallicFactor\":0,\"roughnessFactor\":1}}}}],",
+            "\"textures\":[{{\"sampler\":0,\"source\":0}}],\"samplers\":[{{\"magFilter\":9729,\"minFilter\":9987,\"wrapS\":33071,\"wrapT\":33071}}],",
+            "\"images\":[{}],",
+            "\"accessors\":[",
+            "{{\"bufferView\":0,\"componentType\":5126,\"count\":{},\"type\":\"VEC3\",\"min\":[{},{},{}],\"max\":[{},{},{}]}},",
+            "{{\"bufferView\":1,\"componentType\":5126,\"count\":{},\"type\":\"VEC3\"}},",
+            "{{\"bufferView\":2,\"componentType\":5126,\"count\":{},\"type\":\"VEC2\"}},",
+            "{{\"bufferView\":3,\"componentType\":5125,\"count\":{},\"type\":\"SCALAR\"}}],",
+            "\"bufferViews\":[{}],\"buffers\":[{}]}}"
+        ),
+        image,
+        vertex_count, min[0], min[1], min[2], max[0], max[1], max[2],
+        vertex_count,
+        vertex_count,
+        mesh.triangles.len() * 3,
+        buffer_views.join(","),
+        buffer
+    );
+    Ok((json, if embed { Vec::new() } else { bin }))
+}
+
+/// Standalone .gltf with the geometry and texture as base64 data uris.
+pub fn save_gltf(name: &str, mesh: &Mesh, texture: &Buf) -> Result<()> {
+    let (json, _) = gltf_document(mesh, texture, true)?;
+    std::fs::write(name, json).map_err(Error::io(name))
+}
+
+/// Single binary .glb with the geometry and png texture packed in the bin chunk.
+pub fn save_glb(name: &str, mesh: &Mesh, texture: &Buf) -> Result<()> {
+    let (json, bin) = gltf_document(mesh, texture, false)?;
+    let mut json: Vec<u8> = json.into_bytes();
+    while !json.len().is_multiple_of(4) {
+        json.push(b' ');
+    }
+    write_glb(name, &json, &bin).map_err(Error::io(name))
+}
+
+fn write_glb(name: &str, json: &[u8], bin: &[u8]) -> std::io::Result<()> {
+    let mut file = BufWriter::new(File::create(name)?);
+    file.write_all(b"glTF")?;
+    file.write_all(&2u32.to_le_bytes())?;
+    file.write_all(&((12 + 8 + json.len() + 8 + bin.len()) as u32).to_le_bytes())?;
+    file.write_all(&(json.len() as u32).to_le_bytes())?;
+    file.write_all(b"JSON")?;
+    file.write_all(json)?;
+    file.write_all(&(bin.len() as u32).to_le_bytes())?;
+    file.write_all(b"BIN\0")?;
+    file.write_all(bin)?;
+    file.flush()
+}
@@ -0,0 +1,66 @@
+//! Saving maps and heightfields to disk.
+
+mod animation;
+mod gltf;
+mod model;
+mod svg;
+mod tiles;
+
+pub use animation::save_animation;
+pub use gltf::{save_glb, save_gltf};
+pub use model::{save_obj, save_ply, save_stl};
+pub use svg::{save_svg, SvgLayer};
+pub use tiles::{save_tiles, TilePyramid, TileScheme};
+
+use image::ImageBuffer;
+
+use crate::{Buf, Error, Field, Result};
+
+/// Reads an image as an 8 bit rgb map, whatever format it is in.
+pub fn load(name: &str) -> Result<Buf> {
+    Ok(image::open(name).map_err(Error::image(name))?.to_rgb8())
+}
+
+/// Reads a grayscale heightmap at up to 16 bit precision, color images are converted to gray first.
+pub fn load_field(name: &str) -> Result<Field> {
+    let input = image::open(name).map_err(Error::image(name))?.to_luma16();
+    let mut buffer: Field = ImageBuffer::new(input.width(), input.height());
+    for (height, pixel) in buffer.pixels_mut().zip(input.pixels()) {
+        *height = image::Luma([pixel[0] as f32 / 65535.0]);
+    }
+    Ok(buffer)
+}
+
+/// 8 bit rgb image, the format is picked from the file extension.
+pub fn save(name: &str, data: &Buf) -> Result<()> {
+    image::save_buffer(name, data, data.width(), data.height(), image::ExtendedColorType::Rgb8).map_err(Error::image(name))
+}
+
+fn field_to_u16(input: &Field) -> Vec<u16> {
+    input.pixels().map(|height| (height[0].clamp(0.0, 1.0) * 65535.0).round() as u16).collect()
+}
+
+/// 16 bit grayscale heightmap, the format is picked from the file extension (use .png).
+pub fn save_16(name: &str, data: &Field) -> Result<()> {
+    // the samples always fill the image, so from_raw can't come back empty
+    let buffer: ImageBuffer<image::Luma<u16>, Vec<u16>> = ImageBuffer::from_raw(data.width(), data.height(), field_to_u16(data)).unwrap();
+    buffer.save(name).map_err(Error::image(name))
+}
+
+/// Headerless .raw / .r16, row by row from the top left, as Unity and Unreal import them.
+pub fn save_raw16(name: &str, data: &Field, big_endian: bool) -> Result<()> {
+    let mut bytes: Vec<u8> = Vec::with_capacity((data.width() * data.height() * 2) as usize);
+    for height in field_to_u16(data) {
+        bytes.extend(if big_endian { height.to_be_bytes() } else { height.to_le_bytes() });
+    }
+    std::fs::write(name, bytes).map_err(Error::io(name))
+}
+
+/// Headerless 32 bit float heights (.r32), same layout as [`save_raw16`].
+pub fn save_raw32(name: &str, data: &Field, big_endian: bool) -> Result<()> {
+    let mut bytes: Vec<u8> = Vec::with_capacity((data.width() * data.height() * 4) as usize);
+    for height in data.pixels() {
+        bytes.extend(if big_endian { height[0].to_be_bytes() } else { height[0].to_le_bytes() });
+    }
+    std::fs::write(name, bytes).map_err(Error::io(name))
+}
@@ -0,0 +1,92 @@
+//! Mesh export to OBJ, STL and PLY.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::error::require;
+use crate::mesh::{face_normal, Mesh};
+use crate::{Buf, Error, Result};
+
+/// Wavefront obj plus a .mtl next to it that maps `texture` (a path relative to the obj) onto the surface.
+pub fn save_obj(name: &str, mesh: &Mesh, texture: &str) -> Result<()> {
+    let material_path = std::path::Path::new(name).with_extension("mtl");
+    let material_name = material_path.file_name().map(|file| file.to_string_lossy()).unwrap_or_default();
+    std::fs::write(&material_path, format!("newmtl terrain\nKa 1 1 1\nKd 1 1 1\nKs 0 0 0\nmap_Kd {texture}\n"))
+        .map_err(Error::io(&material_path))?;
+    write_obj(name, mesh, &material_name).map_err(Error::io(name))
+}
+
+fn write_obj(name: &str, mesh: &Mesh, material_name: &str) -> std::io::Result<()> {
+    let mut file = BufWriter::new(File::create(name)?);
+    writeln!(file, "mtllib {material_name}")?;
+    for position in &mesh.positions {
+        writeln!(file, "v {} {} {}", position[0], position[1], position[2])?;
+    }
+    // obj puts v = 0 at the bottom of the texture
+    for uv in &mesh.uvs {
+        writeln!(file, "vt {} {}", uv[0], 1.0 - uv[1])?;
+    }
+    for normal in &mesh.normals {
+        writeln!(file, "vn {} {} {}", normal[0], normal[1], normal[2])?;
+    }
+    writeln!(file, "usemtl terrain")?;
+    for triangle in &mesh.triangles {
+        let [a, b, c] = triangle.map(|index| index + 1);
+        writeln!(file, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}")?;
+    }
+    file.flush()
+}
+
+/// Binary stl, converted to z up like slicers expect. See [`solidify`](crate::mesh::solidify) for printing.
+pub fn save_stl(name: &str, mesh: &Mesh) -> Result<()> {
+    write_stl(name, mesh).map_err(Error::io(name))
+}
+
+fn write_stl(name: &str, mesh: &Mesh) -> std::io::Result<()> {
+    let z_up = |p: [f32; 3]| [p[0], -p[2], p[1]];
+    let mut file = BufWriter::new(File::create(name)?);
+    file.write_all(&[0; 80])?;
+    file.write_all(&(mesh.triangles.len() as u32).to_le_bytes())?;
+    for triangle in &mesh.triangles {
+        let corners = triangle.map(|index| z_up(mesh.positions[index as usize]));
+        let normal = face_normal(corners[0], corners[1], corners[2]);
+        for vector in [normal, corners[0], corners[1], corners[2]] {
+            for component in vector {
+                file.write_all(&component.to_le_bytes())?;
+            }
+        }
+        file.write_all(&0u16.to_le_bytes())?;
+    }
+    file.flush()
+}
+
+/// Binary ply with each vertex colored by the pixel of `colors` under its uv.
+pub fn save_ply(name: &str, mesh: &Mesh, colors: &Buf) -> Result<()> {
+    require(colors.width() > 0 && colors.height() > 0, || "ply colors can't come from an empty image".to_string())?;
+    write_ply(name, mesh, colors).map_err(Error::io(name))
+}
+
+fn write_ply(name: &str, mesh: &Mesh, colors: &Buf) -> std::io::Result<()> {
+    let mut file = BufWriter::new(File::create(name)?);
+    write!(
+        file,
+        "ply\nformat binary_little_endian 1.0\nelement vertex {}\nproperty float x\nproperty float y\nproperty float z\nproperty uchar red\nproperty uchar green\nproperty uchar blue\nelement face {}\nproperty list uchar int vertex_indices\nend_header\n",
+        mesh.positions.len(),
+        mesh.triangles.len()
+    )?;
+    for (position, uv) in mesh.positions.iter().zip(&mesh.uvs) {
+        for component in position {
+            file.write_all(&component.to_le_bytes())?;
+        }
+        let x = (uv[0] * (colors.width() - 1) as f32).round() as u32;
+        let y = (uv[1] * (colors.height() - 1) as f32).round() as u32;
+        file.write_all(&colors.get_pixel(x, y).0)?;
+    }
+    for triangle in &mesh.triangles {
+        file.write_all(&[3])?;
+        for index in triangle {
+            file.write_all(&(*index as i32).to_le_bytes())?;
+        }
+    }
+    file.flush()
+}
@@ -0,0 +1,45 @@
+//! SVG export of traced paths.
+
+use crate::vector::Path;
+use crate::{Error, Result};
+
+/// A named group of paths that share a stroke in the svg output.
+pub struct SvgLayer {
+    /// Id of the group, for styling it afterwards.
+    pub name: String,
+    /// Stroke color.
+    pub stroke: [u8; 3],
+    /// Stroke width in pixels.
+    pub stroke_width: f32,
+    /// Paths in the group.
+    pub paths: Vec<Path>,
+}
+
+/// Writes `layers` in order, later layers draw on top.
+pub fn save_svg(name: &str, width: u32, height: u32, layers: &[SvgLayer]) -> Result<()> {
+    let mut document: String = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">\n");
+    for layer in layers {
+        document.push_str(&format!(
+            "<g id=\"{}\" fill=\"none\" stroke=\"#{:02x}{:02x}{:02x}\" stroke-width=\"{}\" stroke-linejoin=\"round\" stroke-linecap=\"round\">\n",
+            layer.name, layer.stroke[0], layer.stroke[1], layer.stroke[2], layer.stroke_width
+        ));
+        for path in &layer.paths {
+            if path.len() < 2 {
+                continue;
+            }
+            let closed = path.len() > 2 && path[0] == path[path.len() - 1];
+            let points = if closed { &path[..path.len() - 1] } else { &path[..] };
+            let mut d: String = format!("M{:.2} {:.2}", points[0].0, points[0].1);
+            for point in &points[1..] {
+                d.push_str(&format!(" L{:.2} {:.2}", point.0, point.1));
+            }
+            if closed {
+                d.push_str(" Z");
+            }
+            document.push_str(&format!("<path d=\"{d}\"/>\n"));
+        }
+        document.push_str("</g>\n");
+    }
+    document.push_str("</svg>\n");
+    std::fs::write(name, document).map_err(Error::io(name))
+}
@@ -0,0 +1,67 @@
+//! Slippy map tile pyramids for web map viewers.
+
+use std::path::Path;
+
+use crate::error::require;
+use crate::{Buf, Error, Result};
+
+/// How tile rows are numbered.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TileScheme {
+    /// Row 0 at the top, what most web maps use.
+    Xyz,
+    /// Row 0 at the bottom.
+    Tms,
+}
+
+/// Layout of a tile pyramid.
+pub struct TilePyramid {
+    /// Lowest zoom level written.
+    pub min_zoom: u8,
+    /// Highest zoom level written, zoom z has 2^z x 2^z tiles.
+    pub max_zoom: u8,
+    /// Width and height of every tile in pixels.
+    pub tile_size: u32,
+    /// Row numbering of the file names.
+    pub scheme: TileScheme,
+    /// World pixels covered by the single zoom 0 tile, written to the manifest as the bounds.
+    pub world_size: f64,
+}
+
+/// Writes `dir/<z>/<x>/<y>.png` for every tile of the pyramid plus `dir/tiles.json` describing it.
+/// `render` is given the zoom, column and row of a tile with row 0 at the top, whatever the scheme.
+/// Tiles are rendered a zoom level at a time, column by column, starting from tile 0, 0.
+pub fn save_tiles(dir: &str, pyramid: &TilePyramid, mut render: impl FnMut(u8, u32, u32) -> Result<Buf>) -> Result<()> {
+    require(pyramid.min_zoom <= pyramid.max_zoom, || format!("the min zoom {} is above the max zoom {}", pyramid.min_zoom, pyramid.max_zoom))?;
+    require(pyramid.max_zoom < 32, || format!("zoom levels go up to 31, got {}", pyramid.max_zoom))?;
+    for zoom in pyramid.min_zoom..=pyramid.max_zoom {
+        let tiles: u32 = 1 << zoom;
+        for x in 0..tiles {
+            let column = Path::new(dir).join(zoom.to_string()).join(x.to_string());
+            std::fs::create_dir_all(&column).map_err(Error::io(&column))?;
+            for y in 0..tiles {
+                let tile: Buf = render(zoom, x, y)?;
+                require(tile.dimensions() == (pyramid.tile_size, pyramid.tile_size), || {
+                    format!("tiles should be {0}x{0}, got {1}x{2}", pyramid.tile_size, tile.width(), tile.height())
+                })?;
+                let row = if pyramid.scheme == TileScheme::Tms { tiles - 1 - y } else { y };
+                let path = column.join(format!("{row}.png"));
+                tile.save(&path).map_err(Error::image(&path))?;
+            }
+        }
+    }
+    let manifest: String = format!(
+        concat!(
+            "{{\n  \"scheme\": \"{}\",\n  \"tiles\": \"{{z}}/{{x}}/{{y}}.png\",\n  \"tile_size\": {},\n",
+            "  \"min_zoom\": {},\n  \"max_zoom\": {},\n  \"bounds\": [0, 0, {}, {}]\n}}\n"
+        ),
+        if pyramid.scheme == TileScheme::Tms { "tms" } else { "xyz" },
+        pyramid.tile_size,
+        pyramid.min_zoom,
+        pyramid.max_zoom,
+        pyramid.world_size,
+        pyramid.world_size
+    );
+    let path = Path::new(dir).join("tiles.json");
+    std::fs::write(&path, manifest).map_err(Error::io(&path))
+}
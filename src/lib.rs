@@ -0,0 +1,34 @@
+//! Procedural map generation: noise, image operations, simulations and exporters for the maps they make.
+//!
+//! Maps are 8 bit rgb images ([`Buf`]), grayscale ones keep their value in the red channel and masks are
+//! white on black. Heightfields that need more than 256 levels are [`Field`]s with heights in 0.0..=1.0.
+//!
+//! Functions that can fail on bad sizes or arguments, or while writing files, return the crate [`Result`] instead of panicking.
+
+#![warn(missing_docs)]
+
+pub mod color;
+pub mod distance;
+mod error;
+pub mod io;
+pub mod mesh;
+pub mod morphology;
+pub mod noise;
+pub mod ops;
+pub mod pipeline;
+pub mod planet;
+pub mod projection;
+pub mod regions;
+pub mod sim;
+pub mod terrain;
+pub mod vector;
+pub mod world;
+
+pub use error::{Error, Result};
+
+use image::ImageBuffer;
+
+/// 8 bit rgb map.
+pub type Buf = ImageBuffer<image::Rgb<u8>, Vec<u8>>;
+/// Heightfield with 0.0..=1.0 heights, used where 256 levels aren't enough.
+pub type Field = ImageBuffer<image::Luma<f32>, Vec<f32>>;
@@ -1,558 +1,778 @@
-#![allow(dead_code)]
-#![allow(unused_variables)]
-use std::panic::PanicHookInfo;
+use std::collections::HashMap;
 
-use image::{ImageBuffer};
-use rand::{self, random, Rng, SeedableRng};
 use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use titan::color::{terrain_colors, BandScaling, SEA_BLUE4};
+use titan::io::{load, load_field, save, save_16, save_animation, save_glb, save_obj, save_ply, save_raw16, save_raw32, save_stl, save_svg, save_tiles, SvgLayer, TilePyramid, TileScheme};
+use titan::mesh::{heightfield_mesh, solidify, Mesh};
+use titan::noise::{
+    fractal_value, fractal_value_field, generate_noisemap_binary, generate_noisemap_bw, tileable_fractal_value, tileable_fractal_value_field, tileable_voronoi_from_points, voronoi_from_points,
+    voronoi_labels, voronoi_points,
+};
+use titan::ops::{field_to_buf, interpolate_smoothing_field, normalize, threshhold};
+use titan::pipeline::{parse_pipeline, run_pipeline, Pipeline, PipelineEvent};
+use titan::planet::{clip_to_globe, cube_map_fields, orthographic_field, CUBE_FACES};
+use titan::projection::{clip_to_projection, planet_map_field, reproject_field, reproject_image, Projection, Resampling};
+use titan::regions::{fill_small_holes, label_regions, remove_small_regions, Connectivity};
+use titan::sim::{dla_mountain, dla_with, DlaOptions, DlaSeed};
+use titan::terrain::{aspect_map, normal_map, plan_curvature_map, profile_curvature_map, slope_map};
+use titan::vector::{contour_lines, region_borders, simplify_paths, Path};
+use titan::world::{chunk_field, tile_field, time_slice_field, WorldNoise};
+use titan::{Buf, Error, Field, Result};
+
+// a --flag a command accepts, an empty default means it is off unless given
+// switches take no value and are "true" when present
+struct Flag {
+    name: &'static str,
+    default: &'static str,
+    help: &'static str,
+    switch: bool,
+}
 
-type Buf = ImageBuffer<image::Rgb<u8>, Vec<u8>>;
-
-const BLACK: [u8; 3] = [0, 0, 0];
-const WHITE: [u8; 3] = [255, 255, 255];
-const BLUE: [u8; 3] = [0, 0, 255];
-const GREEN: [u8; 3] = [0, 255, 0];
-const RED: [u8; 3] = [255, 0, 0];
-const SEA_BLUE1: [u8; 3] = [0, 131, 255];
-const SEA_BLUE2: [u8; 3] = [0, 107, 209];
-const SEA_BLUE3: [u8; 3] = [0, 81, 158];
-const SEA_BLUE4: [u8; 3] = [0, 61, 120];
-const SEA_BLUE5: [u8; 3] = [0, 47, 92];
-
-/* 
-fn print_type_of<T>(_: &T) {
-    println!("{}", std::any::type_name::<T>());
-}
-*/
-
-fn create_solid_image(width: u32, height: u32, color: [u8; 3]) -> Buf{
-    let mut buffer: Buf = image::ImageBuffer::new(width, height);
-    for (_x, _y, pixel) in buffer.enumerate_pixels_mut() {
-        *pixel = image::Rgb(color);
-    }
-    buffer
+struct Command {
+    name: &'static str,
+    about: &'static str,
+    flags: &'static [Flag],
+    run: fn(&Flags) -> Result<()>,
+}
+
+type Flags = HashMap<&'static str, String>;
+
+const fn flag(name: &'static str, default: &'static str, help: &'static str) -> Flag {
+    Flag { name, default, help, switch: false }
+}
+
+const fn switch(name: &'static str, help: &'static str) -> Flag {
+    Flag { name, default: "false", help, switch: true }
 }
 
-// probability dicates the chance that a pixel will be white
-// the lower the number, the lower the chance
-fn generate_noisemap_binary(width: u32, height: u32, probability: u8) -> Buf{
-    let mut buffer: Buf = image::ImageBuffer::new(width, height);
-    for (_x, _y, pixel) in buffer.enumerate_pixels_mut() {
-        let number: u8 = rand::random();
-        if number < probability {
-            *pixel = image::Rgb([255, 255, 255]);
+const SEED_HELP: &str = "seed for the random generator, random when left out";
+
+const COMMANDS: &[Command] = &[
+    Command {
+        name: "terrain",
+        about: "fractal value noise colored into water, land and snow",
+        run: run_terrain,
+        flags: &[
+            flag("width", "1024", "width of the map in pixels"),
+            flag("height", "1024", "height of the map in pixels"),
+            flag("seed", "", SEED_HELP),
+            flag("output", "final.png", "colored map to write"),
+            flag("water-level", "64", "heights below this are water (1-255)"),
+            flag("mountain-level", "196", "heights from this up are snow (1-255)"),
+            flag("points", "3", "lattice points per side in the first octave"),
+            flag("inc", "3", "how many times more points each octave has"),
+            flag("octaves", "6", "number of octaves"),
+            switch("tileable", "make the map repeat seamlessly, --points becomes the period in lattice cells"),
+            switch("islands", "print how many islands and lakes the map has and where the largest landmass is"),
+            flag("min-island", "0", "islands smaller than this many pixels sink into the sea"),
+            flag("min-lake", "0", "lakes smaller than this many pixels are filled in with land"),
+            flag("relief", "64", "how many pixels tall full brightness is for slopes and meshes"),
+            flag("heightmap", "", "16 bit heightmap, .r16 and .r32 write headerless raw, anything else png"),
+            switch("big-endian", "write .r16 / .r32 heightmaps big endian"),
+            flag("svg", "", "coastline and contour vector map"),
+            flag("normal", "", "tangent space normal map"),
+            flag("slope", "", "slope map in degrees"),
+            flag("aspect", "", "aspect map"),
+            flag("profile-curvature", "", "profile curvature map"),
+            flag("plan-curvature", "", "plan curvature map"),
+            flag("mesh-step", "4", "pixels between mesh vertices"),
+            flag("obj", "", "wavefront obj mesh textured with --output"),
+            flag("ply", "", "ply mesh with vertex colors"),
+            flag("glb", "", "binary gltf mesh with the map embedded"),
+            flag("stl", "", "solid binary stl mesh for printing"),
+            flag("base", "8", "thickness below sea floor of the stl in pixels"),
+        ],
+    },
+    Command {
+        name: "run",
+        about: "evaluate a pipeline file of layers and operations",
+        run: run_pipeline_file,
+        flags: &[
+            flag("pipeline", "", "pipeline file to run"),
+            flag("seed", "", "overrides the seed in the pipeline, random when neither is given"),
+            flag("cache", ".titan-cache", "directory that evaluated layers are kept in between runs"),
+            switch("no-cache", "evaluate every layer from scratch and don't write the cache"),
+            flag("dump", "", "comma separated layers to write as images for debugging, or all"),
+            flag("dump-dir", "dump", "directory the dumped layers are written to"),
+        ],
+    },
+    Command {
+        name: "voronoi",
+        about: "distance to the closest of points x points random sites",
+        run: run_voronoi,
+        flags: &[
+            flag("width", "1024", "width of the image in pixels"),
+            flag("height", "1024", "height of the image in pixels"),
+            flag("seed", "", SEED_HELP),
+            flag("output", "voronoi.png", "image to write"),
+            flag("points", "8", "sites per side"),
+            switch("normalize", "stretch the distances to use the full brightness range"),
+            switch("tileable", "measure distances across the edges so the image repeats seamlessly"),
+            flag("svg", "", "also write the cell borders as svg polylines, traced without wrapping"),
+        ],
+    },
+    Command {
+        name: "dla",
+        about: "diffusion limited aggregation grown from the center or from given seeds",
+        run: run_dla,
+        flags: &[
+            flag("width", "256", "width of the image in pixels"),
+            flag("height", "256", "height of the image in pixels"),
+            flag("seed", "", SEED_HELP),
+            flag("output", "dla.png", "image to write"),
+            flag("ratio", "10", "percent of the image to fill (0-100)"),
+            flag("start-points", "", "pixels to grow from instead of the center, like \"10,20 30,40\""),
+            flag("start-lines", "", "lines to grow from, like \"0,255,255,255\" for the bottom edge"),
+            flag("start-mask", "", "image whose white pixels are grown from, the size of the output"),
+            flag("sticking", "1", "chance that a walker sticks when it touches the cluster (above 0, up to 1)"),
+            switch("eight-neighbours", "diagonal neighbours count as touching"),
+            flag("drift", "0,0", "pull on every step in x and y, together at most 1 in size"),
+            flag("age", "", "also write the cluster colored by when each pixel stuck, blue first to red last"),
+            flag("trunks", "", "also write the cluster with branches thickened by how much hangs off them"),
+            flag("trunk-radius", "4", "radius of the thickest trunk in --trunks"),
+            flag("branches", "", "also write the tree as svg polylines"),
+            flag("animation", "", "also write the growth as a .gif or .png (apng) animation"),
+            flag("frames", "48", "frames in the animation"),
+            flag("frame-delay", "80", "milliseconds every frame of the animation is shown"),
+        ],
+    },
+    Command {
+        name: "mountain",
+        about: "mountain heightmap with ridges along a dla tree, grown and blurred a level at a time",
+        run: run_mountain,
+        flags: &[
+            flag("width", "512", "width of the heightmap in pixels"),
+            flag("height", "512", "height of the heightmap in pixels"),
+            flag("seed", "", SEED_HELP),
+            flag("output", "mountain.png", "16 bit heightmap to write"),
+            flag("levels", "4", "how many times the tree is scaled up, it starts this many halvings smaller"),
+            flag("ratio", "15", "percent of the image the tree covers at every level (1-100)"),
+        ],
+    },
+    Command {
+        name: "value",
+        about: "value noise, fractal when given more than one octave",
+        run: run_value,
+        flags: &[
+            flag("width", "1024", "width of the image in pixels"),
+            flag("height", "1024", "height of the image in pixels"),
+            flag("seed", "", SEED_HELP),
+            flag("output", "value.png", "image to write"),
+            flag("points-wide", "9", "lattice points across in the first octave"),
+            flag("points-tall", "9", "lattice points down in the first octave"),
+            flag("inc", "3", "how many times more points each octave has"),
+            flag("octaves", "1", "number of octaves"),
+            switch("tileable", "make the image repeat seamlessly, the point counts become the period in lattice cells"),
+        ],
+    },
+    Command {
+        name: "world",
+        about: "chunks of an endless world that line up when generated separately",
+        run: run_world,
+        flags: &[
+            flag("seed", "", SEED_HELP),
+            flag("chunk-x", "0", "column of the first chunk, can be negative"),
+            flag("chunk-y", "0", "row of the first chunk, can be negative"),
+            flag("chunks-wide", "1", "how many chunks across to generate"),
+            flag("chunks-tall", "1", "how many chunks down to generate"),
+            flag("chunk-size", "256", "width and height of a chunk in pixels"),
+            flag("cell-size", "256", "pixels per lattice cell in the first octave"),
+            flag("inc", "3", "how many times smaller the cells of each octave are"),
+            flag("octaves", "6", "number of octaves"),
+            flag("water-level", "112", "heights below this are water (1-255)"),
+            flag("mountain-level", "160", "heights from this up are snow (1-255)"),
+            flag("output-dir", "chunks", "directory the chunks are written to as <x>_<y>.png"),
+            switch("heights", "also write every chunk's 16 bit heightmap as <x>_<y>_height.png"),
+        ],
+    },
+    Command {
+        name: "tiles",
+        about: "z/x/y png tile pyramid of a world for web map viewers",
+        run: run_tiles,
+        flags: &[
+            flag("seed", "", SEED_HELP),
+            flag("min-zoom", "0", "lowest zoom level"),
+            flag("max-zoom", "4", "highest zoom level, every level has 4 times the tiles of the last"),
+            flag("tile-size", "256", "width and height of a tile in pixels"),
+            flag("world-size", "1024", "world pixels covered by the zoom 0 tile"),
+            flag("cell-size", "256", "world pixels per lattice cell in the first octave"),
+            flag("inc", "2", "how many times smaller the cells of each octave are"),
+            flag("octaves", "4", "octaves at zoom 0, deeper zooms add more"),
+            flag("water-level", "112", "heights below this are water (1-255)"),
+            flag("mountain-level", "160", "heights from this up are snow (1-255)"),
+            flag("scheme", "xyz", "xyz numbers rows from the top, tms from the bottom"),
+            flag("output-dir", "tiles", "directory the pyramid and its tiles.json are written to"),
+        ],
+    },
+    Command {
+        name: "planet",
+        about: "a whole planet from noise on a sphere, as a flat map, cube map or globe",
+        run: run_planet,
+        flags: &[
+            flag("seed", "", SEED_HELP),
+            flag("cell-size", "0.5", "lattice cell size in the first octave, in planet radii"),
+            flag("inc", "2", "how many times smaller the cells of each octave are"),
+            flag("octaves", "8", "number of octaves"),
+            flag("water-level", "124", "heights below this are water (1-255)"),
+            flag("mountain-level", "164", "heights from this up are snow (1-255)"),
+            flag("width", "1024", "width of the map, the height follows from the projection"),
+            flag("projection", "equirectangular", "equirectangular, mercator, mollweide or azimuthal-equidistant"),
+            flag("output", "planet.png", "map to write"),
+            flag("heightmap", "", "16 bit heightmap in the same projection as the map"),
+            flag("cube", "", "write the six cube map faces as <cube>_px.png, <cube>_nx.png and so on"),
+            flag("cube-size", "512", "width and height of a cube map face"),
+            flag("globe", "", "orthographic view of the globe"),
+            flag("globe-size", "512", "width and height of the globe view"),
+            flag("longitude", "0", "longitude the globe view looks at, in degrees"),
+            flag("latitude", "20", "latitude the globe view looks at, in degrees"),
+        ],
+    },
+    Command {
+        name: "reproject",
+        about: "resample a world map from one projection into another",
+        run: run_reproject,
+        flags: &[
+            flag("input", "", "world map to read"),
+            flag("from", "equirectangular", "projection of the input, equirectangular, mercator, mollweide or azimuthal-equidistant"),
+            flag("to", "mollweide", "projection to write"),
+            flag("width", "", "width of the output, the input width when left out"),
+            flag("height", "", "height of the output, follows from the projection when left out"),
+            flag("resampling", "bicubic", "bilinear or bicubic"),
+            switch("heights", "read the input as a grayscale heightmap and write a 16 bit one"),
+            flag("output", "reprojected.png", "map to write"),
+        ],
+    },
+    Command {
+        name: "slices",
+        about: "3d value noise stepped through time, written as a .gif or .png (apng) animation",
+        run: run_slices,
+        flags: &[
+            flag("width", "256", "width of the animation in pixels"),
+            flag("height", "256", "height of the animation in pixels"),
+            flag("seed", "", SEED_HELP),
+            flag("cell-size", "64", "pixels per lattice cell in the first octave, also in time"),
+            flag("inc", "2", "how many times smaller the cells of each octave are"),
+            flag("octaves", "5", "number of octaves"),
+            flag("frames", "48", "number of frames"),
+            flag("step", "2", "time between frames, in pixels of the first octave's cells"),
+            flag("frame-delay", "80", "milliseconds every frame is shown"),
+            flag("water-level", "112", "heights below this are water (1-255)"),
+            flag("mountain-level", "160", "heights from this up are snow (1-255)"),
+            switch("heights", "write the grayscale heights instead of colors"),
+            flag("output", "slices.gif", "animation to write"),
+        ],
+    },
+    Command {
+        name: "noise",
+        about: "white noise, grayscale or black and white",
+        run: run_noise,
+        flags: &[
+            flag("width", "1024", "width of the image in pixels"),
+            flag("height", "1024", "height of the image in pixels"),
+            flag("seed", "", SEED_HELP),
+            flag("output", "noise.png", "image to write"),
+            flag("probability", "", "make a black and white map where a pixel is white with chance probability / 256"),
+        ],
+    },
+];
+
+fn print_usage() {
+    println!("usage: titan <command> [--flag value]...\n");
+    println!("commands:");
+    for command in COMMANDS {
+        println!("  {:<10}{}", command.name, command.about);
+    }
+    println!("\nrun 'titan <command> --help' for the flags of a command");
+}
+
+fn print_command_help(command: &Command) {
+    println!("usage: titan {} [--flag value]...\n", command.name);
+    println!("{}\n", command.about);
+    println!("flags:");
+    for flag in command.flags {
+        let name = if flag.switch { format!("--{}", flag.name) } else { format!("--{} <value>", flag.name) };
+        if flag.default.is_empty() || flag.switch {
+            println!("  {:<28}{}", name, flag.help);
         } else {
-            *pixel = image::Rgb([0, 0, 0]);
+            println!("  {:<28}{} [default: {}]", name, flag.help, flag.default);
         }
     }
-    buffer
 }
 
-fn generate_noisemap_bw(width: u32, height: u32) -> Buf{
-    let mut buffer: Buf = image::ImageBuffer::new(width, height);
-    for (_x, _y, pixel) in buffer.enumerate_pixels_mut() {
-        let number: u8 = rand::random();
-        *pixel = image::Rgb([number, number, number]);
-    }
-    buffer
-}
-
-// upscales an input buffer of size width, height by a scale of factor
-fn upscale_image_square(factor: u32, input: Buf) -> Buf {
-    let output_width: u32 = input.width() * factor;
-    let output_height: u32 = input.height() * factor;
-    let mut buffer: Buf = image::ImageBuffer::new(output_width, output_height);
-    for(x, y, pixel) in input.enumerate_pixels() {
-        for sub_y in 0..factor{
-            for sub_x in 0..factor{
-                let red = pixel[0];
-                let green = pixel[1];
-                let blue = pixel[2];
-                if (red != 255 && red != 0) && (green != 255 && green != 0) && (blue != 255 && blue != 0){
-                    println!("Red: {}, Green: {}, Blue: {}", red, green, blue);
-                }
-                let buffer_pixel = buffer.get_pixel_mut(sub_x + (x * factor), sub_y + (y * factor));
-                *buffer_pixel = *pixel;
+// accepts --name value, --name=value and bare switches
+fn parse_flags(command: &Command, args: &[String]) -> Result<Flags> {
+    let mut flags: Flags = command.flags.iter().map(|flag| (flag.name, flag.default.to_string())).collect();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let Some(stripped) = arg.strip_prefix("--") else {
+            return Err(Error::InvalidArgument(format!("unexpected argument '{arg}'")));
+        };
+        let (name, inline) = match stripped.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (stripped, None),
+        };
+        let Some(flag) = command.flags.iter().find(|flag| flag.name == name) else {
+            return Err(Error::InvalidArgument(format!("unknown flag '--{name}' for {}", command.name)));
+        };
+        let value = if flag.switch {
+            if inline.is_some() {
+                return Err(Error::InvalidArgument(format!("--{name} does not take a value")));
             }
-        }
-    }
-    buffer
-}
-
-fn upscale_image_lines(factor: u32, input: Buf) -> Buf {
-    let mut buffer: Buf = image::ImageBuffer::new(input.width() * (factor * 2 + 1), input.height() * (factor * 2 + 1));
-    for (x, y, pixel) in input.enumerate_pixels() {
-        if(pixel[0] == 255){
-            for i in 0..=factor * 2 {
-                let sub_pixel = buffer.get_pixel_mut((x  * (factor * 2 + 1)) + i - factor, y  * (factor * 2 + 1));
-                *sub_pixel = image::Rgb(WHITE);
-                let sub_pixel2 = buffer.get_pixel_mut((x  * (factor * 2 + 1)), y  * (factor * 2 + 1) + i - factor);
-                *sub_pixel2 = image::Rgb(WHITE);
+            "true".to_string()
+        } else {
+            match inline.or_else(|| args.next().cloned()) {
+                Some(value) => value,
+                None => return Err(Error::InvalidArgument(format!("--{name} needs a value"))),
             }
-        }
+        };
+        flags.insert(flag.name, value);
     }
-
-    buffer
+    Ok(flags)
 }
 
-fn invert(input: Buf) -> Buf {
-    let mut buffer: Buf = image::ImageBuffer::new(input.width(), input.height());
-    for (x, y, pixel) in buffer.enumerate_pixels_mut() {
-        let input_pixel = input.get_pixel(x, y);
-        *pixel = image::Rgb([255 - input_pixel[0], 255 - input_pixel[1], 255 - input_pixel[2]]);
-    }
-
-    buffer
-}
-
-fn dla(output_width: u32, output_height: u32, ratio: u32) -> Buf {
-    let mut width: u32 = output_width;
-    let mut height: u32 = output_height;
-    // shrink to correct size
-    let mut buffer: Buf = create_solid_image(width, height, [0, 0, 0]);
-    let mut count: u32 = 4;
-    let mut x: u32;
-    let mut y: u32;
-    // starting coordinates are right around the center
-    let buffer_pixel= buffer.get_pixel_mut(width as u32 / 2, height as u32 / 2);
-    *buffer_pixel = image::Rgb([255, 255, 255]);    
-    loop {
-        loop {
-            x = rand::random();
-            y = rand::random();
-            x = x % width;
-            y = y % height;
-            let test_pixel = buffer.get_pixel(x, y);
-            if test_pixel[0] == 0 {
-                break;
-            }
-        }
-        loop {
-            // check the pixel for collision
-            if y != 0 {
-                let test_pixel = buffer.get_pixel(x, y - 1);
-                // if the pixel is white, place it
-                if test_pixel[0] == 255 {
-                    let pixel = buffer.get_pixel_mut(x, y);
-                    *pixel = image::Rgb([255, 255, 255]);
-                    break;
-                }
-            }
+fn get<T: std::str::FromStr>(flags: &Flags, name: &str) -> Result<T> {
+    flags[name].parse().map_err(|_| Error::InvalidArgument(format!("invalid value '{}' for --{name}", flags[name])))
+}
 
-            if y != height - 1 {
-                let test_pixel = buffer.get_pixel(x, y + 1);
-                // if the pixel is white, place it
-                if test_pixel[0] == 255 {
-                    let pixel = buffer.get_pixel_mut(x, y);
-                    *pixel = image::Rgb([255, 255, 255]);
-                    break;
-                }
-            }
+// a flag that was left empty comes back as None
+fn get_optional(flags: &Flags, name: &str) -> Option<String> {
+    Some(flags[name].clone()).filter(|value| !value.is_empty())
+}
 
-            if x != width - 1 {
-                let test_pixel = buffer.get_pixel(x + 1, y);
-                // if the pixel is white, place it
-                if test_pixel[0] == 255 {
-                    let pixel = buffer.get_pixel_mut(x, y);
-                    *pixel = image::Rgb([255, 255, 255]);
-                    break;
-                }
-            }
-            if x != 0 {
-                let test_pixel = buffer.get_pixel(x - 1, y);
-                // if the pixel is white, place it
-                if test_pixel[0] == 255 {
-                    let pixel = buffer.get_pixel_mut(x, y);
-                    *pixel = image::Rgb([255, 255, 255]);
-                    break;
-                }
-            }
+fn require(condition: bool, message: &str) -> Result<()> {
+    if condition { Ok(()) } else { Err(Error::InvalidArgument(message.to_string())) }
+}
 
-            // move the pixel
-            let direction: u8 = rand::random();
-            // move up
-            if direction % 4 == 0 && y != 0 {
-                y -= 1;
-            // move right
-            } else if  direction % 4 == 1 && x != width - 1 {
-                x += 1;
-            }
-            // move down
-            else if direction % 4 == 2 && y != height - 1 {
-                y += 1;
-            }
-            // move left
-            else if direction % 4 == 3 && x != 0 {
-                x -= 1;
-            }
-        }
-        //println!("{} / {}", count, (ratio * (width * height)) / 100);
-        count += 1;
-        // desired number of pixels is reached
-        if ((count as f32 / (width as f32 * height as f32)) * 100.0) as u32 >= ratio || count >= width * height {
-            break;
-        }
-    }
-    buffer
+// --seed, then the fallback, then a random one, printed so the run can be repeated
+fn seed(flags: &Flags, fallback: Option<u64>) -> Result<u64> {
+    let seed: u64 = match (get_optional(flags, "seed"), fallback) {
+        (Some(_), _) => get(flags, "seed")?,
+        (None, Some(seed)) => seed,
+        (None, None) => rand::rng().random(),
+    };
+    println!("Using seed {seed}");
+    Ok(seed)
 }
 
-fn and(input1: Buf, input2: Buf) -> Buf{
-    let mut buffer: Buf = image::ImageBuffer::new(input1.width(), input2.height());
-    for (x, y, pixel) in buffer.enumerate_pixels_mut() {
-        if input1.get_pixel(x, y)[0] == 255 && input2.get_pixel(x, y)[0] == 255 {
-            *pixel = image::Rgb(WHITE);
-        } else {
-            *pixel = image::Rgb(BLACK);
-        }
-    }
+fn seeded_rng(flags: &Flags) -> Result<StdRng> {
+    Ok(StdRng::seed_from_u64(seed(flags, None)?))
+}
 
-    buffer
+// the seed is all a world depends on besides its coordinates
+fn world_noise(flags: &Flags) -> Result<WorldNoise> {
+    Ok(WorldNoise { seed: seed(flags, None)?, cell_size: get(flags, "cell-size")?, inc: get(flags, "inc")?, octaves: get(flags, "octaves")? })
 }
 
-fn or(input1: Buf, input2: Buf) -> Buf{
-    let mut buffer: Buf = image::ImageBuffer::new(input1.width(), input2.height());
-    for (x, y, pixel) in buffer.enumerate_pixels_mut() {
-        if input1.get_pixel(x, y)[0] == 255 || input2.get_pixel(x, y)[0] == 255 {
-            *pixel = image::Rgb(WHITE);
-        } else {
-            *pixel = image::Rgb(BLACK);
-        }
-    }
+// space separated groups of `count` comma separated numbers, "1,2 3,4"
+fn get_groups<T: std::str::FromStr>(flags: &Flags, name: &str, count: usize) -> Result<Vec<Vec<T>>> {
+    let invalid = || Error::InvalidArgument(format!("invalid value '{}' for --{name}, expected groups of {count} numbers", flags[name]));
+    flags[name]
+        .split_whitespace()
+        .map(|group| {
+            let numbers: Vec<T> = group.split(',').map(|number| number.trim().parse().map_err(|_| invalid())).collect::<Result<_>>()?;
+            if numbers.len() == count { Ok(numbers) } else { Err(invalid()) }
+        })
+        .collect()
+}
 
-    buffer
+fn size(flags: &Flags, minimum: u32) -> Result<(u32, u32)> {
+    let width: u32 = get(flags, "width")?;
+    let height: u32 = get(flags, "height")?;
+    require(width >= minimum && height >= minimum, &format!("--width and --height must be at least {minimum}"))?;
+    Ok((width, height))
 }
 
-fn not(target: [u8; 3], input: Buf) -> Buf{
-    let mut buffer: Buf = image::ImageBuffer::new(input.width(), input.height());
-    for (x, y, pixel) in buffer.enumerate_pixels_mut() {
-        let sub_pixel = input.get_pixel(x, y);
-        if target[0] == sub_pixel[0] && target[1] == sub_pixel[1] && target[2] == sub_pixel[2] {
-            *pixel = image::Rgb(BLACK);
-        } else {
-            *pixel = image::Rgb(WHITE);
-        }
+// pipeline errors are only line numbers, so they get the file in front
+fn in_file(path: &str) -> impl FnOnce(Error) -> Error + '_ {
+    move |error| match error {
+        Error::Pipeline(message) => Error::Pipeline(format!("{path}: {message}")),
+        error => error,
     }
-
-    buffer
 }
 
-fn subtract(input1: Buf, input2: Buf) -> Buf{
-    let mut buffer: Buf = image::ImageBuffer::new(input1.width(), input2.height());
-    for (x, y, pixel) in buffer.enumerate_pixels_mut() {
-        if input1.get_pixel(x, y)[0] == 255 && input2.get_pixel(x, y)[0] == 255 {
-            *pixel = image::Rgb(BLACK);
-        } else if input1.get_pixel(x, y)[0] == 255{
-            *pixel = image::Rgb(WHITE);
+fn run_terrain(flags: &Flags) -> Result<()> {
+    let (width, height) = size(flags, 2)?;
+    let name: String = flags["output"].clone();
+    let water_level: u8 = get(flags, "water-level")?;
+    let mountain_level: u8 = get(flags, "mountain-level")?;
+    let points: u32 = get(flags, "points")?;
+    let inc: u32 = get(flags, "inc")?;
+    let octaves: u8 = get(flags, "octaves")?;
+    let relief: f32 = get(flags, "relief")?;
+    let mesh_step: u32 = get(flags, "mesh-step")?;
+    let base: f32 = get(flags, "base")?;
+    require(water_level >= 1, "--water-level must be at least 1")?;
+    require(mountain_level > water_level, "--mountain-level must be above --water-level")?;
+    require(octaves >= 1, "--octaves must be at least 1")?;
+    require(inc >= 1, "--inc must be at least 1")?;
+    require(relief > 0.0, "--relief must be above 0")?;
+    require(mesh_step >= 1, "--mesh-step must be at least 1")?;
+    let mut rng: StdRng = seeded_rng(flags)?;
+
+    // let mut heights: Field = fractal_value_field(width, height, 9, 9, 3, 5, &mut rng);
+    let fractal_value = if get(flags, "tileable")? { tileable_fractal_value_field } else { fractal_value_field };
+    let mut heights: Field = fractal_value(width, height, points, points, inc, octaves, &mut rng)?;
+    interpolate_smoothing_field(&mut heights, 0.0, 1.0);
+    clean_coast(&mut heights, water_level, get(flags, "min-island")?, get(flags, "min-lake")?);
+    let data: Buf = field_to_buf(&heights);
+
+    let water: Buf = terrain_colors(&data, water_level, mountain_level, BandScaling::Normalized)?;
+    save(&name, &water)?;
+    if get(flags, "islands")? {
+        print_islands(&data, water_level);
+    }
+
+    // 3d versions of the map, textured / colored with the final image
+    if ["obj", "ply", "glb", "stl"].iter().any(|format| get_optional(flags, format).is_some()) {
+        let mut mesh: Mesh = heightfield_mesh(&heights, relief, mesh_step)?;
+        if let Some(path) = get_optional(flags, "obj") {
+            let obj_dir = std::path::Path::new(&path).parent().unwrap_or(std::path::Path::new(""));
+            save_obj(&path, &mesh, &relative_path(&name, obj_dir)?)?;
+        }
+        if let Some(path) = get_optional(flags, "ply") {
+            save_ply(&path, &mesh, &water)?;
+        }
+        if let Some(path) = get_optional(flags, "glb") {
+            save_glb(&path, &mesh, &water)?;
+        }
+        if let Some(path) = get_optional(flags, "stl") {
+            solidify(&mut mesh, base);
+            save_stl(&path, &mesh)?;
+        }
+    }
+
+    // full precision heightmaps for engines that terrace on 8 bit input
+    if let Some(path) = get_optional(flags, "heightmap") {
+        let big_endian: bool = get(flags, "big-endian")?;
+        if path.ends_with(".r16") || path.ends_with(".raw") {
+            save_raw16(&path, &heights, big_endian)?;
+        } else if path.ends_with(".r32") {
+            save_raw32(&path, &heights, big_endian)?;
         } else {
-            *pixel = image::Rgb(BLACK);
+            save_16(&path, &heights)?;
         }
     }
 
-    buffer
-}
-
-fn expand(radius: u32, color: [u8; 3], input: Buf) -> Buf {
-    let mut buffer: Buf = image::ImageBuffer::new(input.width(), input.height());
-    for (x, y, pixel) in input.enumerate_pixels() {
-        if pixel[0] == color[0] && pixel[1] == color[1] && pixel[2] == color[2] {
-            for sub_y in (y as i32 - radius as i32)..(y as i32 + radius as i32 * 2){
-                for sub_x in (x as i32 - radius as i32)..(x as i32 + radius as i32 * 2){
-                    if sub_y < input.height() as i32 && sub_x < input.width() as i32 && sub_y > 0 && sub_x > 0{
-                        // if cell is valid, change it's color
-                        let sub_pixel = buffer.get_pixel_mut(sub_x as u32, sub_y as u32);
-                        *sub_pixel = image::Rgb(color);
-                    }
-                }
-            }
-        }
+    // derived layers for engines, relief is how many pixels tall full brightness is
+    if let Some(path) = get_optional(flags, "normal") {
+        save(&path, &normal_map(&heights, relief, false))?;
     }
-
-    buffer
-}
-
-// ! basic implementation of voronoi noise
-// TODO: add normalization so it looks better
-// divides a board into "cells"
-// each cell is assigned a single white point at a random location relative to that cell
-// then, each non-white pixel is assigned a brightness depeding on how far it is from that point
-fn voronoi(width: u32, height: u32, points: u32) -> Buf {
-    let mut buffer: Buf = image::ImageBuffer::new(width, height);
-    let cell_width: u32 = width / points;
-    let cell_height: u32 = height / points;
-    let mut coords: Vec<(u32, u32)> = Vec::new();
-    // create random points (this squares the number of points from input unfortunately)
-    // redo this later by using some sort of wrapping-esq approach for an exact 
-    for iter_y in 0..points {
-        for iter_x in 0..points {
-            let mut x: u32 = rand::random();
-            let mut y: u32 = rand::random();
-            x = (x % cell_width) + (iter_x * cell_width);
-            y = (y % cell_height) + (iter_y * cell_height);
-            coords.push((x, y));
-            let pixel = buffer.get_pixel_mut(x, y);
-            *pixel = image::Rgb([255, 255, 255]);
-        }
+    if let Some(path) = get_optional(flags, "slope") {
+        save(&path, &slope_map(&heights, relief))?;
     }
-    // iterating through each pixel to find minimum distance
-    // this is really slow but it's fine
-    // for every pixel
-    for (x, y, pixel) in buffer.enumerate_pixels_mut() {
-        // loop through untilyou find a white pixel
-        let mut min_dist = 1000000000;
-        for (x2, y2) in coords.clone() {
-            let dist = ((((x as i32 - x2 as i32) * (x as i32 - x2 as i32)) + ((y as i32 - y2 as i32) * (y as i32 - y2 as i32))) as f64).sqrt() as i32;
-            if dist < min_dist as i32 {
-                min_dist = dist as u32;
-            }
-        }
-
-        if min_dist >= 255 {
-            *pixel = image::Rgb([255, 255, 255]);
-        } else {
-            *pixel = image::Rgb([min_dist as u8, min_dist as u8, min_dist as u8]);
-        }
+    if let Some(path) = get_optional(flags, "aspect") {
+        save(&path, &aspect_map(&heights, relief))?;
     }
-    buffer
-}
-
-fn linear_interpolate(x1: f32, y1: f32, x2: f32, y2: f32, x: f32) -> f32 {
-    // prevent divide by zero errors
-    if x1 == x2 {
-        return y1 as f32;
+    if let Some(path) = get_optional(flags, "profile-curvature") {
+        save(&path, &profile_curvature_map(&heights, relief))?;
     }
-    y1 as f32 + (x as f32 - x1 as f32) * ((y1 as f32 - y2 as f32) / (x1 as f32 - x2 as f32))
-}
-
-fn overlay(input: &mut Buf, input2: Buf){
-    for (x, y, pixel) in input2.enumerate_pixels() {
-        if pixel[0] != 0 || pixel[1] != 0 || pixel[2] != 0 {
-            let sub_pixel = input.get_pixel_mut(x, y);
-            *sub_pixel = image::Rgb([pixel[0], pixel[1], pixel[2]]);
-        }
-        
+    if let Some(path) = get_optional(flags, "plan-curvature") {
+        save(&path, &plan_curvature_map(&heights, relief))?;
     }
-}
 
-fn threshhold(input: &Buf, lower: u8, higher: u8, preserve: bool) -> Buf {
-    let mut buffer: Buf = image::ImageBuffer::new(input.width(), input.height());
-    for (x, y, pixel) in input.enumerate_pixels() {
-        if pixel[0] >= lower && pixel[0] <= higher {
-            if(preserve){
-                let sub_pixel = buffer.get_pixel_mut(x, y);
-                *sub_pixel = image::Rgb([pixel[0], pixel[1], pixel[2]]);
-            } else {
-                let sub_pixel = buffer.get_pixel_mut(x, y);
-                *sub_pixel = image::Rgb([255, 255, 255]);
-            }
+    // vector version of the same map for print
+    if let Some(path) = get_optional(flags, "svg") {
+        let coastline: Vec<Path> = simplify_paths(contour_lines(&data, water_level as f32 - 0.5), 0.75);
+        let mut contours: Vec<Path> = Vec::new();
+        for level in (water_level as u32 + 16..256).step_by(16) {
+            contours.extend(simplify_paths(contour_lines(&data, level as f32 - 0.5), 0.75));
         }
+        save_svg(&path, width, height, &[
+            SvgLayer { name: "contours".to_string(), stroke: [150, 120, 90], stroke_width: 0.5, paths: contours },
+            SvgLayer { name: "coastline".to_string(), stroke: SEA_BLUE4, stroke_width: 1.5, paths: coastline },
+        ])?;
     }
-    buffer
+    Ok(())
 }
 
-fn recolor_proportion(input: Buf, mut red: f32, mut green: f32, mut blue: f32) -> Buf {
-    red = 255.0 / red;
-    green = 255.0 / green;
-    blue = 255.0 / blue;
-    let mut buffer: Buf = image::ImageBuffer::new(input.width(), input.height());
-    for (x, y, pixel) in input.enumerate_pixels() {
-        let sub_pixel = buffer.get_pixel_mut(x, y);
-        *sub_pixel = image::Rgb([(pixel[0] as f32 / red) as u8, (pixel[0] as f32 / green) as u8, (pixel[0] as f32 / blue) as u8]);
-    }
-    buffer
+// how to get to an existing file from dir, with / between the parts like obj and mtl files expect
+fn relative_path(file: &str, dir: &std::path::Path) -> Result<String> {
+    let dir = if dir.as_os_str().is_empty() { std::path::Path::new(".") } else { dir };
+    let canonical = |path: &std::path::Path| std::fs::canonicalize(path).map_err(|source| Error::Io { path: path.into(), source });
+    let file = canonical(std::path::Path::new(file))?;
+    let dir = canonical(dir)?;
+    let file: Vec<_> = file.components().collect();
+    let dir: Vec<_> = dir.components().collect();
+    let shared: usize = file.iter().zip(&dir).take_while(|(first, second)| first == second).count();
+    let mut parts: Vec<String> = vec!["..".to_string(); dir.len() - shared];
+    parts.extend(file[shared..].iter().map(|part| part.as_os_str().to_string_lossy().into_owned()));
+    Ok(parts.join("/"))
 }
 
-fn recolor_solid(input: Buf, red: u8, green: u8, blue: u8) -> Buf {
-    let mut buffer: Buf = image::ImageBuffer::new(input.width(), input.height());
-    for (x, y, pixel) in input.enumerate_pixels() {
-        let sub_pixel = buffer.get_pixel_mut(x, y);
-        if sub_pixel[0] == 255 && sub_pixel[1] == 255 && sub_pixel[2] == 255 {
-            *sub_pixel = image::Rgb([red, green, blue]);
+// islands and lakes below the minimum sizes are flattened to just below or at the water level
+fn clean_coast(heights: &mut Field, water_level: u8, min_island: usize, min_lake: usize) {
+    if min_island == 0 && min_lake == 0 {
+        return;
+    }
+    let land: Buf = threshhold(&field_to_buf(heights), water_level, 255, false);
+    let cleaned: Buf = fill_small_holes(&remove_small_regions(&land, min_island, Connectivity::Four), min_lake, Connectivity::Four);
+    // half a step into the level so quantizing back to 8 bits lands on the right side of it
+    let (sea, shore) = ((water_level as f32 - 0.5) / 255.0, (water_level as f32 + 0.5) / 255.0);
+    for ((height, before), after) in heights.pixels_mut().zip(land.pixels()).zip(cleaned.pixels()) {
+        if before[0] == 255 && after[0] == 0 {
+            height[0] = sea;
+        } else if before[0] == 0 && after[0] == 255 {
+            height[0] = shore;
         }
     }
-    buffer
 }
 
-fn normalize(input: &mut Buf) {
-    let mut lightest: u8 = 0;
-    for (_x, _y, pixel) in input.enumerate_pixels() {
-        if pixel[0] > lightest {
-            lightest = pixel[0];
-        }
+// land touching only at a corner is separate islands, but water flows through diagonal gaps
+fn print_islands(data: &Buf, water_level: u8) {
+    let land = label_regions(&threshhold(data, water_level, 255, false), Connectivity::Four);
+    let water = label_regions(&threshhold(data, 0, water_level - 1, false), Connectivity::Eight);
+    println!("{} islands, {} bodies of water", land.regions.len(), water.regions.len());
+    if let Some(largest) = land.largest() {
+        let (left, top, right, bottom) = largest.bounds;
+        println!(
+            "largest landmass: {} pixels centered on {:.0}, {:.0}, spanning {left}, {top} to {right}, {bottom} with a coastline of {} pixels",
+            largest.area, largest.centroid.0, largest.centroid.1, largest.perimeter
+        );
     }
+}
 
-    let ratio: f32 = 255.0 / lightest as f32;
+fn run_pipeline_file(flags: &Flags) -> Result<()> {
+    let Some(path) = get_optional(flags, "pipeline") else {
+        return Err(Error::InvalidArgument("--pipeline is required".to_string()));
+    };
+    let text: String = std::fs::read_to_string(&path).map_err(|source| Error::Io { path: path.clone().into(), source })?;
+    let pipeline: Pipeline = parse_pipeline(&text).map_err(in_file(&path))?;
+    let seed: u64 = seed(flags, pipeline.seed)?;
+    let no_cache: bool = get(flags, "no-cache")?;
+    let cache_dir = if no_cache { None } else { Some(std::path::PathBuf::from(&flags["cache"])) };
+    let dumps: Vec<String> = match get_optional(flags, "dump").as_deref() {
+        None => Vec::new(),
+        Some("all") => pipeline.nodes.iter().map(|node| node.name.clone()).collect(),
+        Some(list) => list.split(',').map(|name| name.trim().to_string()).collect(),
+    };
+    run_pipeline(&pipeline, seed, cache_dir, &dumps, &flags["dump-dir"], |event| match event {
+        PipelineEvent::Cached(name) => println!("Using cached {name}"),
+        PipelineEvent::Evaluating(name) => println!("Evaluating {name}"),
+        PipelineEvent::CacheWriteFailed(name, path, error) => eprintln!("warning: couldn't cache {name} in {}: {error}", path.display()),
+    })
+    .map_err(in_file(&path))
+}
 
-    for (_x, _y, pixel) in input.enumerate_pixels_mut() {
-        let color: u16 = (pixel[0] as f32 * ratio) as u16;
-        *pixel = image::Rgb([color as u8, color as u8, color as u8]);
-    }
+fn run_voronoi(flags: &Flags) -> Result<()> {
+    let (width, height) = size(flags, 1)?;
+    let points: u32 = get(flags, "points")?;
+    let mut rng: StdRng = seeded_rng(flags)?;
+    let coords: Vec<(u32, u32)> = voronoi_points(width, height, points, &mut rng)?;
+    let voronoi = if get(flags, "tileable")? { tileable_voronoi_from_points } else { voronoi_from_points };
+    let mut data: Buf = voronoi(width, height, &coords);
+    if get(flags, "normalize")? {
+        normalize(&mut data);
+    }
+    save(&flags["output"], &data)?;
+    if let Some(path) = get_optional(flags, "svg") {
+        let borders: Vec<Path> = simplify_paths(region_borders(&voronoi_labels(width, height, &coords), width, height)?, 0.75);
+        save_svg(&path, width, height, &[SvgLayer { name: "cells".to_string(), stroke: [255, 255, 255], stroke_width: 1.0, paths: borders }])?;
+    }
+    Ok(())
 }
 
-fn save(name: &str, data: &Buf){
-    image::save_buffer(name, &data, data.width(), data.height(), image::ExtendedColorType::Rgb8).unwrap();
-}
-
-// ! implementation of value noise
-fn value(width: u32, height: u32, points_wide: u32, points_tall: u32) -> Buf {
-    let mut buffer: Buf = image::ImageBuffer::new(width, height);
-    let cell_width: f32 = (width as f32 - 1.0) / (points_wide as f32 - 1.0);
-    let cell_height: f32 = (height as f32 - 1.0) / (points_tall as f32 - 1.0);
-    let mut coords: Vec<Vec<(u32, u32, u8)>> = Vec::new();
-    let mut temp: Vec<(u32, u32, u8)> = Vec::new();
-    // placing original points
-    for iter_y in 0..points_tall {
-        for iter_x in 0..points_wide {
-            let x: u32 = (iter_x as f32 * cell_width) as u32;
-            let y: u32 = (iter_y as f32 * cell_height) as u32;
-            let value: u8 = random();
-            temp.push((x, y, value));
-            let pixel = buffer.get_pixel_mut(x, y);
-            *pixel = image::Rgb([value, value, value]);
-        }
-        // create a 2d list of each coordinate pair
-        coords.push(temp.clone());
-        temp = [].to_vec();
-    }
-    // interpolate horizontally
-    
-    for sub_list in coords {
-        let mut x: u32 = 0;
-        for i in 0..points_wide - 1 {
-            for k in sub_list[i as usize].0..sub_list[i as usize + 1].0 {
-                let value: u8 = linear_interpolate(sub_list[i as usize].0 as f32, sub_list[i as usize].2 as f32, sub_list[i as usize + 1].0 as f32, sub_list[i as usize + 1].2 as f32, x as f32) as u8;
-                let pixel = buffer.get_pixel_mut(x, sub_list[i as usize].1);
-                *pixel = image::Rgb([value as u8, value as u8, value as u8]);
-                x += 1;
-            }
-        }
-    }
-    // interpolate vertically
-    let mut output: Buf = buffer.clone();
-    for i in 0..points_tall - 1 {
-        for x in 0..width {
-            
-            // get the chunk
-            let lower: u32 = (i as f32 * cell_height) as u32;
-            let higher: u32 = ((i + 1) as f32 * cell_height) as u32;
-            for y in lower..higher{
-                let pixel_1 = buffer.get_pixel(x, lower);
-                let pixel_2 = buffer.get_pixel(x, higher);
-                let target_pixel = output.get_pixel_mut(x, y);
-                let value: u8 = linear_interpolate(lower as f32, pixel_1[0] as f32, higher as f32, pixel_2[0] as f32, y as f32) as u8;
-                *target_pixel = image::Rgb([value, value, value])
-            }
-        }
-    }
-    output
+fn run_dla(flags: &Flags) -> Result<()> {
+    let (width, height) = size(flags, 1)?;
+    let ratio: u32 = get(flags, "ratio")?;
+    let mut seeds: Vec<DlaSeed> = Vec::new();
+    seeds.extend(get_groups(flags, "start-points", 2)?.into_iter().map(|point| DlaSeed::Point(point[0], point[1])));
+    seeds.extend(get_groups(flags, "start-lines", 4)?.into_iter().map(|line| DlaSeed::Line((line[0], line[1]), (line[2], line[3]))));
+    if let Some(mask) = get_optional(flags, "start-mask") {
+        seeds.push(DlaSeed::Mask(load(&mask)?));
+    }
+    let drift = match get_groups::<f32>(flags, "drift", 2)?.as_slice() {
+        [drift] => (drift[0], drift[1]),
+        _ => return Err(Error::InvalidArgument(format!("invalid value '{}' for --drift, expected x,y", flags["drift"]))),
+    };
+    let options = DlaOptions { seeds, sticking: get(flags, "sticking")?, eight_neighbours: get(flags, "eight-neighbours")?, drift };
+    let mut rng: StdRng = seeded_rng(flags)?;
+    let growth = dla_with(width, height, ratio, &options, &mut rng)?;
+    if let Some(path) = get_optional(flags, "age") {
+        save(&path, &growth.age_image([0, 64, 255], [255, 32, 0]))?;
+    }
+    if let Some(path) = get_optional(flags, "trunks") {
+        save(&path, &growth.trunk_image(get(flags, "trunk-radius")?))?;
+    }
+    if let Some(path) = get_optional(flags, "branches") {
+        let layer = SvgLayer { name: "branches".to_string(), stroke: [255, 255, 255], stroke_width: 1.0, paths: growth.branches() };
+        save_svg(&path, width, height, &[layer])?;
+    }
+    if let Some(path) = get_optional(flags, "animation") {
+        let frames: usize = get(flags, "frames")?;
+        require(frames >= 1, "--frames must be at least 1")?;
+        // pixels stuck by the end of every frame, the last frame is the finished cluster
+        let images: Vec<Buf> = (1..=frames).map(|frame| growth.image_at(growth.order.len() * frame / frames)).collect();
+        save_animation(&path, &images, get(flags, "frame-delay")?)?;
+    }
+    save(&flags["output"], &growth.image)
 }
 
-fn scale_noise(input: &mut Buf, level: f32){
-    for (_x, _y, pixel) in input.enumerate_pixels_mut() {
-        let mut red: u32 = ((pixel[0] as f32) * level) as u32;
-        let mut green: u32 = ((pixel[1] as f32) * level) as u32;
-        let mut blue: u32 = ((pixel[2] as f32) * level) as u32;
-        if red > 255 {
-            red = 255;
-        }
-        if green > 255 {
-            green = 255;
+fn run_mountain(flags: &Flags) -> Result<()> {
+    let (width, height) = size(flags, 8)?;
+    let levels: u8 = get(flags, "levels")?;
+    let ratio: u32 = get(flags, "ratio")?;
+    let mut rng: StdRng = seeded_rng(flags)?;
+    save_16(&flags["output"], &dla_mountain(width, height, levels, ratio, &mut rng)?)
+}
 
-        }
-        if blue > 255 {
-            blue = 255;
-        }
-        *pixel = image::Rgb([red as u8, green as u8, blue as u8]);
-    }
+fn run_slices(flags: &Flags) -> Result<()> {
+    let (width, height) = size(flags, 1)?;
+    let frames: u32 = get(flags, "frames")?;
+    let step: f64 = get(flags, "step")?;
+    require(frames >= 1, "--frames must be at least 1")?;
+    let water_level: u8 = get(flags, "water-level")?;
+    let mountain_level: u8 = get(flags, "mountain-level")?;
+    let heights: bool = get(flags, "heights")?;
+    let noise: WorldNoise = world_noise(flags)?;
+    let mut images: Vec<Buf> = Vec::with_capacity(frames as usize);
+    for frame in 0..frames {
+        let slice = field_to_buf(&time_slice_field(&noise, width, height, frame as f64 * step)?);
+        images.push(if heights { slice } else { terrain_colors(&slice, water_level, mountain_level, BandScaling::Fixed)? });
+    }
+    save_animation(&flags["output"], &images, get(flags, "frame-delay")?)
 }
 
-fn linear_scale_noise(input: &mut Buf, level: i32){
-    for (_x, _y, pixel) in input.enumerate_pixels_mut() {
-        let mut red: i32 = pixel[0] as i32 + level;
-        let mut green: i32 = pixel[1] as i32 + level;
-        let mut blue: i32 = pixel[2] as i32 + level;
-        if red > 255 {
-            red = 255;
-        } else if red < 0 {
-            red = 0;
-        }
-        if green > 255 {
-            green = 255;
-        } else if green < 0 {
-            green = 0;
-        }
-        if blue > 255 {
-            blue = 255;
-        } else if blue < 0 {
-            blue = 0;
-        }
-        *pixel = image::Rgb([red as u8, green as u8, blue as u8]);
-    }
+fn run_value(flags: &Flags) -> Result<()> {
+    let (width, height) = size(flags, 2)?;
+    let points_wide: u32 = get(flags, "points-wide")?;
+    let points_tall: u32 = get(flags, "points-tall")?;
+    let inc: u32 = get(flags, "inc")?;
+    let octaves: u8 = get(flags, "octaves")?;
+    require(octaves >= 1, "--octaves must be at least 1")?;
+    require(inc >= 1, "--inc must be at least 1")?;
+    let mut rng: StdRng = seeded_rng(flags)?;
+    let fractal_value = if get(flags, "tileable")? { tileable_fractal_value } else { fractal_value };
+    save(&flags["output"], &fractal_value(width, height, points_wide, points_tall, inc, octaves, &mut rng)?)
 }
 
-fn add(input: &mut Buf, input2: Buf){
-    for (x, y, pixel) in input.enumerate_pixels_mut() {
-        let second_pixel = input2.get_pixel(x, y);
-        let mut red: u32 = pixel[0] as u32 + second_pixel[0] as u32;
-        if red > 255 {
-            red = 255;
-        }
-        let mut green: u32 = pixel[1] as u32 + second_pixel[1] as u32;
-        if green > 255 {
-            green = 255;
-        }
-        let mut blue: u32 = pixel[2] as u32 + second_pixel[2] as u32;
-        if blue > 255 {
-            blue = 255;
+fn run_world(flags: &Flags) -> Result<()> {
+    let chunk_x: i64 = get(flags, "chunk-x")?;
+    let chunk_y: i64 = get(flags, "chunk-y")?;
+    let chunks_wide: u32 = get(flags, "chunks-wide")?;
+    let chunks_tall: u32 = get(flags, "chunks-tall")?;
+    let chunk_size: u32 = get(flags, "chunk-size")?;
+    let water_level: u8 = get(flags, "water-level")?;
+    let mountain_level: u8 = get(flags, "mountain-level")?;
+    let dir: &str = &flags["output-dir"];
+    require(chunk_size >= 1, "--chunk-size must be at least 1")?;
+    let noise: WorldNoise = world_noise(flags)?;
+    std::fs::create_dir_all(dir).map_err(|source| Error::Io { path: dir.into(), source })?;
+    for y in chunk_y..chunk_y + chunks_tall as i64 {
+        for x in chunk_x..chunk_x + chunks_wide as i64 {
+            println!("Generating chunk {x}, {y}");
+            let heights: Field = chunk_field(&noise, x, y, chunk_size)?;
+            let path = std::path::Path::new(dir);
+            save(&path.join(format!("{x}_{y}.png")).to_string_lossy(), &terrain_colors(&field_to_buf(&heights), water_level, mountain_level, BandScaling::Fixed)?)?;
+            if get(flags, "heights")? {
+                save_16(&path.join(format!("{x}_{y}_height.png")).to_string_lossy(), &heights)?;
+            }
         }
-        *pixel = image::Rgb([red as u8, green as u8, blue as u8]);
     }
+    Ok(())
 }
 
-fn fractal_value(width: u32, height: u32, mut points_wide: u32, mut points_tall: u32, inc: u32, octaves: u8) -> Buf{
-    let mut buffer: Buf = value(width, height, points_wide, points_tall);
-    let mut level: f32 = 0.25;
-    for i in 1..octaves {
-        points_tall *= inc;
-        points_wide *= inc;
-        println!("Adding another octave at {points_wide} by {points_tall}");
-        let mut octave: Buf = value(width, height, points_tall, points_wide);
-        scale_noise(&mut octave, level);
-        level /= 2.0;
-        add(&mut buffer, octave);
-    }
-    buffer
+fn run_tiles(flags: &Flags) -> Result<()> {
+    let scheme: TileScheme = match flags["scheme"].as_str() {
+        "xyz" => TileScheme::Xyz,
+        "tms" => TileScheme::Tms,
+        other => return Err(Error::InvalidArgument(format!("--scheme must be xyz or tms, got '{other}'"))),
+    };
+    let pyramid = TilePyramid {
+        min_zoom: get(flags, "min-zoom")?,
+        max_zoom: get(flags, "max-zoom")?,
+        tile_size: get(flags, "tile-size")?,
+        scheme,
+        world_size: get(flags, "world-size")?,
+    };
+    let water_level: u8 = get(flags, "water-level")?;
+    let mountain_level: u8 = get(flags, "mountain-level")?;
+    require(pyramid.world_size > 0.0, "--world-size must be above 0")?;
+    let noise: WorldNoise = world_noise(flags)?;
+    save_tiles(&flags["output-dir"], &pyramid, |zoom, x, y| {
+        if x == 0 && y == 0 {
+            println!("Rendering zoom {zoom}, {0} by {0} tiles", 1u32 << zoom);
+        }
+        let heights: Field = tile_field(&noise, pyramid.world_size, zoom, x, y, pyramid.tile_size)?;
+        terrain_colors(&field_to_buf(&heights), water_level, mountain_level, BandScaling::Fixed)
+    })
 }
 
-fn interpolate_smoothing(input: &mut Buf, lower: u8, higher: u8){
-    let mut dim: u8 = 255;
-    let mut bright: u8 = 0;
-    for (_x, _y, pixel) in input.enumerate_pixels() {
-        if pixel[0] < dim {
-            dim = pixel[0];
-        }
-        if pixel[0] > bright {
-            bright = pixel[0];
-        }
-    }
-    let lower_ratio: f32;
-    let higher_ratio: f32;
+fn run_planet(flags: &Flags) -> Result<()> {
+    let width: u32 = get(flags, "width")?;
+    let water_level: u8 = get(flags, "water-level")?;
+    let mountain_level: u8 = get(flags, "mountain-level")?;
+    require(width >= 2, "--width must be at least 2")?;
+    let noise: WorldNoise = world_noise(flags)?;
+    let color = |heights: &Field| terrain_colors(&field_to_buf(heights), water_level, mountain_level, BandScaling::Fixed);
+
+    let projection: Projection = get(flags, "projection")?;
+    let heights: Field = planet_map_field(&noise, projection, width, (width as f64 / projection.aspect()).round() as u32)?;
+    let mut map: Buf = color(&heights)?;
+    clip_to_projection(&mut map, projection);
+    save(&flags["output"], &map)?;
+    if let Some(path) = get_optional(flags, "heightmap") {
+        save_16(&path, &heights)?;
+    }
+    if let Some(prefix) = get_optional(flags, "cube") {
+        let faces: [Field; 6] = cube_map_fields(&noise, get(flags, "cube-size")?)?;
+        for (face, name) in faces.iter().zip(CUBE_FACES) {
+            save(&format!("{prefix}_{name}.png"), &color(face)?)?;
+        }
+    }
+    if let Some(path) = get_optional(flags, "globe") {
+        let longitude: f64 = get(flags, "longitude")?;
+        let latitude: f64 = get(flags, "latitude")?;
+        let mut globe: Buf = color(&orthographic_field(&noise, get(flags, "globe-size")?, longitude.to_radians(), latitude.to_radians())?)?;
+        clip_to_globe(&mut globe);
+        save(&path, &globe)?;
+    }
+    Ok(())
+}
 
-    if dim == 0 {
-        lower_ratio = lower as f32;
-    } else {
-        lower_ratio = lower as f32 / dim as f32;
-    }
-    if bright == 0 {
-        higher_ratio = higher as f32;
+fn run_reproject(flags: &Flags) -> Result<()> {
+    let Some(path) = get_optional(flags, "input") else {
+        return Err(Error::InvalidArgument("--input is required".to_string()));
+    };
+    let from: Projection = get(flags, "from")?;
+    let to: Projection = get(flags, "to")?;
+    let resampling: Resampling = get(flags, "resampling")?;
+    let size = |input_width: u32| -> Result<(u32, u32)> {
+        let width: u32 = match get_optional(flags, "width") {
+            Some(_) => get(flags, "width")?,
+            None => input_width,
+        };
+        let height: u32 = match get_optional(flags, "height") {
+            Some(_) => get(flags, "height")?,
+            None => (width as f64 / to.aspect()).round() as u32,
+        };
+        require(width >= 1 && height >= 1, "--width and --height must be at least 1")?;
+        Ok((width, height))
+    };
+    if get(flags, "heights")? {
+        let input: Field = load_field(&path)?;
+        let (width, height) = size(input.width())?;
+        save_16(&flags["output"], &reproject_field(&input, from, to, width, height, resampling)?)
     } else {
-        higher_ratio = higher as f32 / bright as f32;
+        let input: Buf = load(&path)?;
+        let (width, height) = size(input.width())?;
+        save(&flags["output"], &reproject_image(&input, from, to, width, height, resampling)?)
     }
+}
 
-
-    for (_x, _y, pixel) in input.enumerate_pixels_mut() {
-        let color: u8 = (pixel[0] as f32 * linear_interpolate(dim as f32, lower_ratio, bright as f32, higher_ratio, pixel[0] as f32)) as u8;
-        *pixel = image::Rgb([color, color, color]);
-    }
+fn run_noise(flags: &Flags) -> Result<()> {
+    let (width, height) = size(flags, 1)?;
+    let probability: Option<u8> = match get_optional(flags, "probability") {
+        Some(_) => Some(get(flags, "probability")?),
+        None => None,
+    };
+    let mut rng: StdRng = seeded_rng(flags)?;
+    let data: Buf = match probability {
+        Some(probability) => generate_noisemap_binary(width, height, probability, &mut rng),
+        None => generate_noisemap_bw(width, height, &mut rng),
+    };
+    save(&flags["output"], &data)
 }
 
 // TODO: Add Voronoi Noise [X]
@@ -563,38 +783,28 @@ fn interpolate_smoothing(input: &mut Buf, lower: u8, higher: u8){
 // TODO: Reimpliment Into Gaia Maybe? [ ]
 
 fn main() {
-    let width: u32 = 1024;
-    let height: u32 = 1024;
-    let name: &str = "final.png";
-
-    let water_level: u8 = 64; 
-    let mountain_level: u8 = 196;
-    
-    // let mut data: Buf = fractal_value(width, height, 9, 9, 3, 5);
-    let mut data: Buf = fractal_value(width, height, 3, 3, 3, 6);
-    // data = invert(data);
-    // linear_scale_noise(&mut data, 1);
-    // normalize(&mut data);
-    interpolate_smoothing(&mut data, 0, 255);
-
-    /* 
-    */
-
-    let mut snow: Buf = threshhold(&data, mountain_level, 255, true);
-    snow = recolor_proportion(snow, 220.0, 220.0, 220.0);
-
-    let mut land: Buf = threshhold(&data, water_level, mountain_level - 1, true);
-    normalize(&mut land);
-    //land = recolor_proportion(land, 250.0, 227.0, 180.0);
-    land = recolor_proportion(land, 128.0, 128.0, 128.0);
-    let mut water: Buf = threshhold(&data, 0, water_level - 1, true);
-    linear_scale_noise(&mut water, 32);
-    normalize(&mut water);
-    water = recolor_proportion(water, 1.0, 87.0, 255.0);
-
-    overlay(&mut water, land);
-    overlay(&mut water, snow);
-
-    save(name, &water);
-    
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result: Result<()> = match args.first().map(String::as_str) {
+        None => {
+            print_usage();
+            std::process::exit(2);
+        }
+        Some("help" | "--help" | "-h") => {
+            print_usage();
+            Ok(())
+        }
+        Some(name) => match COMMANDS.iter().find(|command| command.name == name) {
+            None => Err(Error::InvalidArgument(format!("unknown command '{name}'"))),
+            Some(command) if args[1..].iter().any(|arg| arg == "--help" || arg == "-h") => {
+                print_command_help(command);
+                Ok(())
+            }
+            Some(command) => parse_flags(command, &args[1..]).and_then(|flags| (command.run)(&flags)),
+        },
+    };
+    if let Err(error) = result {
+        eprintln!("error: {error}");
+        eprintln!("run 'titan --help' for usage");
+        std::process::exit(2);
+    }
 }
@@ -0,0 +1,106 @@
+//! Triangle meshes built from heightfields.
+
+use crate::error::require;
+use crate::{Field, Result};
+
+/// Triangle grid over a heightfield, y is up and the image lies in the x/z plane.
+/// Uvs are in image space (v grows down the image), triangles wind counterclockwise from above.
+pub struct Mesh {
+    /// Vertex positions in pixels.
+    pub positions: Vec<[f32; 3]>,
+    /// Unit normal of every vertex.
+    pub normals: Vec<[f32; 3]>,
+    /// Texture coordinate of every vertex.
+    pub uvs: Vec<[f32; 2]>,
+    /// Vertex indices of every triangle.
+    pub triangles: Vec<[u32; 3]>,
+    /// Vertices across the surface grid, the first `columns * rows` vertices are the grid row by row.
+    pub columns: u32,
+    /// Vertices down the surface grid.
+    pub rows: u32,
+}
+
+// every step-th pixel along one side, always ending on the last pixel
+fn grid_positions(size: u32, step: u32) -> Vec<u32> {
+    let mut positions: Vec<u32> = (0..size).step_by(step.max(1) as usize).collect();
+    if *positions.last().unwrap() != size - 1 {
+        positions.push(size - 1);
+    }
+    positions
+}
+
+/// One vertex every `step` pixels, a height of 1.0 is `vertical_scale` pixels tall.
+pub fn heightfield_mesh(input: &Field, vertical_scale: f32, step: u32) -> Result<Mesh> {
+    require(input.width() > 0 && input.height() > 0, || "can't build a mesh from an empty heightfield".to_string())?;
+    let xs: Vec<u32> = grid_positions(input.width(), step);
+    let ys: Vec<u32> = grid_positions(input.height(), step);
+    let height = |x: i64, y: i64| {
+        let x = x.clamp(0, input.width() as i64 - 1) as u32;
+        let y = y.clamp(0, input.height() as i64 - 1) as u32;
+        input.get_pixel(x, y)[0] * vertical_scale
+    };
+    let mut mesh = Mesh { positions: Vec::new(), normals: Vec::new(), uvs: Vec::new(), triangles: Vec::new(), columns: xs.len() as u32, rows: ys.len() as u32 };
+    for &y in &ys {
+        for &x in &xs {
+            let (ix, iy) = (x as i64, y as i64);
+            let dx = (height(ix + 1, iy) - height(ix - 1, iy)) / 2.0;
+            let dz = (height(ix, iy + 1) - height(ix, iy - 1)) / 2.0;
+            let length = (dx * dx + 1.0 + dz * dz).sqrt();
+            mesh.positions.push([x as f32, height(ix, iy), y as f32]);
+            mesh.normals.push([-dx / length, 1.0 / length, -dz / length]);
+            mesh.uvs.push([x as f32 / (input.width() - 1).max(1) as f32, y as f32 / (input.height() - 1).max(1) as f32]);
+        }
+    }
+    for row in 0..mesh.rows - 1 {
+        for column in 0..mesh.columns - 1 {
+            let top_left = row * mesh.columns + column;
+            let bottom_left = top_left + mesh.columns;
+            mesh.triangles.push([top_left, bottom_left, top_left + 1]);
+            mesh.triangles.push([top_left + 1, bottom_left, bottom_left + 1]);
+        }
+    }
+    Ok(mesh)
+}
+
+/// Closes the surface into a watertight solid for printing,
+/// adding walls down to `thickness` below zero and a flat bottom.
+pub fn solidify(mesh: &mut Mesh, thickness: f32) {
+    let (columns, rows) = (mesh.columns, mesh.rows);
+    // walk the border so the top edge goes left to right, which keeps every wall facing out
+    let mut border: Vec<u32> = Vec::new();
+    border.extend(0..columns);
+    border.extend((1..rows).map(|row| row * columns + columns - 1));
+    border.extend((0..columns - 1).rev().map(|column| (rows - 1) * columns + column));
+    border.extend((1..rows - 1).rev().map(|row| row * columns));
+    let first_bottom = mesh.positions.len() as u32;
+    for &index in &border {
+        let [x, _, z] = mesh.positions[index as usize];
+        mesh.positions.push([x, -thickness, z]);
+        mesh.normals.push([0.0, -1.0, 0.0]);
+        mesh.uvs.push(mesh.uvs[index as usize]);
+    }
+    let [width, _, depth] = mesh.positions[(columns * rows - 1) as usize];
+    let center = mesh.positions.len() as u32;
+    mesh.positions.push([width / 2.0, -thickness, depth / 2.0]);
+    mesh.normals.push([0.0, -1.0, 0.0]);
+    mesh.uvs.push([0.5, 0.5]);
+    for i in 0..border.len() {
+        let next = (i + 1) % border.len();
+        let (top, top_next) = (border[i], border[next]);
+        let (bottom, bottom_next) = (first_bottom + i as u32, first_bottom + next as u32);
+        mesh.triangles.push([top, top_next, bottom_next]);
+        mesh.triangles.push([top, bottom_next, bottom]);
+        mesh.triangles.push([center, bottom, bottom_next]);
+    }
+}
+
+pub(crate) fn face_normal(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> [f32; 3] {
+    let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+    let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+    let normal = [u[1] * v[2] - u[2] * v[1], u[2] * v[0] - u[0] * v[2], u[0] * v[1] - u[1] * v[0]];
+    let length = (normal[0] * normal[0] + normal[1] * normal[1] + normal[2] * normal[2]).sqrt();
+    if length == 0.0 {
+        return [0.0, 0.0, 0.0];
+    }
+    [normal[0] / length, normal[1] / length, normal[2] / length]
+}
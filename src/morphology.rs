@@ -0,0 +1,261 @@
+//! Morphology: growing, shrinking and cleaning up shapes with a structuring element, and thinning them to skeletons.
+//!
+//! Every channel is handled on its own with the max or min under the element, so the same operations work
+//! on white on black masks, grayscale maps and colored images. Pixels off the image are left out, shapes
+//! touching the edges don't shrink away from them.
+
+use std::str::FromStr;
+
+use crate::distance::distance_transform;
+use crate::{Buf, Error, Field, Result};
+
+/// Outline of a structuring element.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Shape {
+    /// Every pixel within `radius` in both x and y.
+    Square,
+    /// Every pixel within `radius` in a straight line.
+    Disk,
+    /// A plus, the row and column through the center out to `radius`.
+    Cross,
+}
+
+impl FromStr for Shape {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Shape> {
+        match name {
+            "square" => Ok(Shape::Square),
+            "disk" => Ok(Shape::Disk),
+            "cross" => Ok(Shape::Cross),
+            _ => Err(Error::InvalidArgument(format!("unknown structuring element '{name}'"))),
+        }
+    }
+}
+
+/// The neighbourhood morphology operations look at around every pixel, centered on it.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct StructuringElement {
+    offsets: Vec<(i64, i64)>,
+}
+
+impl StructuringElement {
+    /// A `shape` reaching `radius` pixels from the center, radius 0 is just the center pixel.
+    pub fn new(shape: Shape, radius: u32) -> StructuringElement {
+        let radius = radius as i64;
+        let mut offsets: Vec<(i64, i64)> = Vec::new();
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                let inside = match shape {
+                    Shape::Square => true,
+                    Shape::Disk => dx * dx + dy * dy <= radius * radius,
+                    Shape::Cross => dx == 0 || dy == 0,
+                };
+                if inside {
+                    offsets.push((dx, dy));
+                }
+            }
+        }
+        StructuringElement { offsets }
+    }
+
+    /// Offsets from the center the element covers.
+    pub fn offsets(&self) -> &[(i64, i64)] {
+        &self.offsets
+    }
+}
+
+// every channel of every pixel set to the max or min of the pixels under the element
+fn rank(input: &Buf, element: &StructuringElement, maximum: bool) -> Buf {
+    let (width, height) = input.dimensions();
+    let mut buffer: Buf = image::ImageBuffer::new(width, height);
+    for (x, y, pixel) in buffer.enumerate_pixels_mut() {
+        let mut value: [u8; 3] = if maximum { [0; 3] } else { [255; 3] };
+        for &(dx, dy) in &element.offsets {
+            let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+            if nx < 0 || ny < 0 || nx >= width as i64 || ny >= height as i64 {
+                continue;
+            }
+            let neighbour = input.get_pixel(nx as u32, ny as u32);
+            for c in 0..3 {
+                value[c] = if maximum { value[c].max(neighbour[c]) } else { value[c].min(neighbour[c]) };
+            }
+        }
+        *pixel = image::Rgb(value);
+    }
+    buffer
+}
+
+// first minus second channel by channel, clamped at 0
+fn difference(first: &Buf, second: &Buf) -> Buf {
+    let mut buffer: Buf = first.clone();
+    for (pixel, other) in buffer.pixels_mut().zip(second.pixels()) {
+        for c in 0..3 {
+            pixel[c] = pixel[c].saturating_sub(other[c]);
+        }
+    }
+    buffer
+}
+
+/// Grows bright areas by the element, every pixel becomes the brightest pixel under it.
+pub fn dilate(input: &Buf, element: &StructuringElement) -> Buf {
+    rank(input, element, true)
+}
+
+/// Shrinks bright areas by the element, every pixel becomes the darkest pixel under it.
+pub fn erode(input: &Buf, element: &StructuringElement) -> Buf {
+    rank(input, element, false)
+}
+
+/// Erodes then dilates: removes bright specks and bridges smaller than the element and keeps the rest.
+pub fn open(input: &Buf, element: &StructuringElement) -> Buf {
+    dilate(&erode(input, element), element)
+}
+
+/// Dilates then erodes: fills dark specks and gaps smaller than the element and keeps the rest.
+pub fn close(input: &Buf, element: &StructuringElement) -> Buf {
+    erode(&dilate(input, element), element)
+}
+
+/// Dilation minus erosion, bright along the edges of shapes and dark inside and outside them.
+pub fn gradient(input: &Buf, element: &StructuringElement) -> Buf {
+    difference(&dilate(input, element), &erode(input, element))
+}
+
+/// The input minus its opening: only the bright details smaller than the element, like peaks and thin ridges.
+pub fn top_hat(input: &Buf, element: &StructuringElement) -> Buf {
+    difference(input, &open(input, element))
+}
+
+/// The closing minus the input: only the dark details smaller than the element, like pits and narrow valleys.
+pub fn black_hat(input: &Buf, element: &StructuringElement) -> Buf {
+    difference(&close(input, element), input)
+}
+
+/// Thins the white areas of `mask` down to lines one pixel wide along their middles with Zhang and Suen's
+/// algorithm, like the spine of a mountain range or the centerline of a river. Shapes stay connected and
+/// keep their holes.
+pub fn skeleton(mask: &Buf) -> Buf {
+    let (width, height) = mask.dimensions();
+    let index = |x: i64, y: i64| y as usize * width as usize + x as usize;
+    let mut white: Vec<bool> = mask.pixels().map(|pixel| pixel[0] == 255).collect();
+    // neighbours clockwise from north, off the image counts as black
+    let neighbours = |white: &[bool], x: i64, y: i64| -> [bool; 8] {
+        [(0, -1), (1, -1), (1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1)].map(|(dx, dy)| {
+            let (nx, ny) = (x + dx, y + dy);
+            nx >= 0 && ny >= 0 && nx < width as i64 && ny < height as i64 && white[index(nx, ny)]
+        })
+    };
+    let mut removed: Vec<usize> = Vec::new();
+    loop {
+        let mut changed = false;
+        for pass in 0..2 {
+            for y in 0..height as i64 {
+                for x in 0..width as i64 {
+                    if !white[index(x, y)] {
+                        continue;
+                    }
+                    let around = neighbours(&white, x, y);
+                    let [north, _, east, _, south, _, west, _] = around;
+                    let count = around.iter().filter(|&&neighbour| neighbour).count();
+                    // black to white steps going once around, more than one means this pixel joins two parts
+                    let steps = (0..8).filter(|&i| !around[i] && around[(i + 1) % 8]).count();
+                    // the first pass peels south east edges and north west corners, the second the opposite
+                    let corner = if pass == 0 { !(east && south && (north || west)) } else { !(north && west && (east || south)) };
+                    if (2..=6).contains(&count) && steps == 1 && corner {
+                        removed.push(index(x, y));
+                    }
+                }
+            }
+            changed |= !removed.is_empty();
+            for index in removed.drain(..) {
+                white[index] = false;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    let mut buffer: Buf = image::ImageBuffer::new(width, height);
+    for (pixel, &white) in buffer.pixels_mut().zip(&white) {
+        *pixel = image::Rgb(if white { [255, 255, 255] } else { [0, 0, 0] });
+    }
+    buffer
+}
+
+/// The [`skeleton`] of `mask` with every skeleton pixel set to its distance to the closest black pixel,
+/// half the width of the shape there, and 0.0 everywhere else. Distances are in pixels.
+/// A mask without black pixels has an infinite distance on every skeleton pixel.
+pub fn medial_axis(mask: &Buf) -> Field {
+    let mut background: Buf = mask.clone();
+    for pixel in background.pixels_mut() {
+        *pixel = image::Rgb(if pixel[0] == 255 { [0, 0, 0] } else { [255, 255, 255] });
+    }
+    let mut distances: Field = distance_transform(&background);
+    for (distance, pixel) in distances.pixels_mut().zip(skeleton(mask).pixels()) {
+        if pixel[0] != 255 {
+            distance[0] = 0.0;
+        }
+    }
+    distances
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mask(width: u32, height: u32, white: impl Fn(u32, u32) -> bool) -> Buf {
+        image::ImageBuffer::from_fn(width, height, |x, y| image::Rgb(if white(x, y) { [255; 3] } else { [0; 3] }))
+    }
+
+    fn square(x: u32, y: u32) -> bool {
+        (3..=6).contains(&x) && (3..=6).contains(&y)
+    }
+
+    #[test]
+    fn dilate_grows_a_square_and_erode_shrinks_it_back() {
+        let element = StructuringElement::new(Shape::Square, 1);
+        let grown: Buf = dilate(&mask(10, 10, square), &element);
+        assert_eq!(grown, mask(10, 10, |x, y| (2..=7).contains(&x) && (2..=7).contains(&y)));
+        assert_eq!(erode(&grown, &element), mask(10, 10, square));
+    }
+
+    #[test]
+    fn open_removes_specks() {
+        let specked: Buf = mask(10, 10, |x, y| square(x, y) || (x, y) == (8, 1));
+        assert_eq!(open(&specked, &StructuringElement::new(Shape::Square, 1)), mask(10, 10, square));
+    }
+
+    #[test]
+    fn gradient_is_only_set_on_edges() {
+        let edges: Buf = gradient(&mask(10, 10, square), &StructuringElement::new(Shape::Cross, 1));
+        let inside = |x: i64, y: i64| (3..=6).contains(&x) && (3..=6).contains(&y);
+        for (x, y, pixel) in edges.enumerate_pixels() {
+            let (x, y) = (x as i64, y as i64);
+            let edge = [(1, 0), (-1, 0), (0, 1), (0, -1)].iter().any(|&(dx, dy)| inside(x + dx, y + dy) != inside(x, y));
+            assert_eq!(pixel[0] != 0, edge, "{x}, {y}");
+        }
+    }
+
+    // 12 by 3 pixels with its middle along row 3
+    fn bar() -> Buf {
+        mask(14, 7, |x, y| (1..=12).contains(&x) && (2..=4).contains(&y))
+    }
+
+    #[test]
+    fn skeleton_thins_a_bar_to_a_line() {
+        let line: Vec<(u32, u32)> = skeleton(&bar()).enumerate_pixels().filter(|(_, _, pixel)| pixel[0] == 255).map(|(x, y, _)| (x, y)).collect();
+        assert!(line.len() >= 8, "{line:?}");
+        assert!(line.iter().all(|&(_, y)| y == 3), "{line:?}");
+        // one unbroken run along the middle row
+        assert!(line.windows(2).all(|pair| pair[1].0 == pair[0].0 + 1), "{line:?}");
+    }
+
+    #[test]
+    fn medial_axis_measures_half_the_width() {
+        let axis: Field = medial_axis(&bar());
+        let along: Vec<(u32, u32, f32)> = axis.enumerate_pixels().filter(|(_, _, distance)| distance[0] != 0.0).map(|(x, y, distance)| (x, y, distance[0])).collect();
+        assert!(along.len() >= 8, "{along:?}");
+        assert!(along.iter().all(|&(_, y, distance)| y == 3 && distance == 2.0), "{along:?}");
+    }
+}
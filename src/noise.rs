@@ -0,0 +1,274 @@
+//! Noise generators: white noise, value noise and voronoi.
+//!
+//! Every generator takes the random generator to draw from, so the same seed always gives the same map.
+//! The `tileable_` versions wrap around at the image edges, so their maps can be repeated on a grid without seams.
+
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use crate::error::{require, Error};
+use crate::ops::{field_to_buf, linear_interpolate};
+use crate::{Buf, Field, Result};
+
+/// Black and white noise, `probability` dictates the chance that a pixel will be white.
+/// The lower the number, the lower the chance.
+pub fn generate_noisemap_binary(width: u32, height: u32, probability: u8, rng: &mut StdRng) -> Buf{
+    let mut buffer: Buf = image::ImageBuffer::new(width, height);
+    for (_x, _y, pixel) in buffer.enumerate_pixels_mut() {
+        let number: u8 = rng.random();
+        if number < probability {
+            *pixel = image::Rgb([255, 255, 255]);
+        } else {
+            *pixel = image::Rgb([0, 0, 0]);
+        }
+    }
+    buffer
+}
+
+/// Grayscale noise, every pixel an independent random brightness.
+pub fn generate_noisemap_bw(width: u32, height: u32, rng: &mut StdRng) -> Buf{
+    let mut buffer: Buf = image::ImageBuffer::new(width, height);
+    for (_x, _y, pixel) in buffer.enumerate_pixels_mut() {
+        let number: u8 = rng.random();
+        *pixel = image::Rgb([number, number, number]);
+    }
+    buffer
+}
+
+/// Basic implementation of voronoi noise.
+///
+/// Divides a board into `points` x `points` cells, each cell is assigned a single point at a random
+/// location inside it, then every pixel is assigned a brightness depending on how far it is from the
+/// closest point (capped at 255).
+// TODO: add normalization so it looks better
+pub fn voronoi(width: u32, height: u32, points: u32, rng: &mut StdRng) -> Result<Buf> {
+    let coords: Vec<(u32, u32)> = voronoi_points(width, height, points, rng)?;
+    Ok(voronoi_from_points(width, height, &coords))
+}
+
+/// Places one random point inside each of the `points` x `points` cells.
+/// Every cell needs to be at least a pixel, so `points` can't be more than the width or height.
+pub fn voronoi_points(width: u32, height: u32, points: u32, rng: &mut StdRng) -> Result<Vec<(u32, u32)>> {
+    require(points >= 1 && points <= width.min(height), || {
+        format!("voronoi needs between 1 and {} points per side for a {width}x{height} image, got {points}", width.min(height))
+    })?;
+    let cell_width: u32 = width / points;
+    let cell_height: u32 = height / points;
+    let mut coords: Vec<(u32, u32)> = Vec::new();
+    // create random points (this squares the number of points from input unfortunately)
+    // redo this later by using some sort of wrapping-esq approach for an exact 
+    for iter_y in 0..points {
+        for iter_x in 0..points {
+            let mut x: u32 = rng.random();
+            let mut y: u32 = rng.random();
+            x = (x % cell_width) + (iter_x * cell_width);
+            y = (y % cell_height) + (iter_y * cell_height);
+            coords.push((x, y));
+        }
+    }
+    Ok(coords)
+}
+
+/// Renders the distance to the closest of `coords`, see [`voronoi`].
+pub fn voronoi_from_points(width: u32, height: u32, coords: &[(u32, u32)]) -> Buf {
+    voronoi_distances(width, height, coords, false)
+}
+
+/// Voronoi that tiles: a site near one edge is also close to the pixels just across the opposite edge.
+pub fn tileable_voronoi(width: u32, height: u32, points: u32, rng: &mut StdRng) -> Result<Buf> {
+    let coords: Vec<(u32, u32)> = voronoi_points(width, height, points, rng)?;
+    Ok(tileable_voronoi_from_points(width, height, &coords))
+}
+
+/// Renders the wrap-around distance to the closest of `coords`, see [`tileable_voronoi`].
+pub fn tileable_voronoi_from_points(width: u32, height: u32, coords: &[(u32, u32)]) -> Buf {
+    voronoi_distances(width, height, coords, true)
+}
+
+// wrap measures distances on a torus, going out one edge comes back in the other
+fn voronoi_distances(width: u32, height: u32, coords: &[(u32, u32)], wrap: bool) -> Buf {
+    let mut buffer: Buf = image::ImageBuffer::new(width, height);
+    // iterating through each pixel to find minimum distance
+    // this is really slow but it's fine
+    // for every pixel
+    for (x, y, pixel) in buffer.enumerate_pixels_mut() {
+        // loop through untilyou find a white pixel
+        let mut min_dist = 1000000000;
+        for &(x2, y2) in coords {
+            let mut dx: i32 = (x as i32 - x2 as i32).abs();
+            let mut dy: i32 = (y as i32 - y2 as i32).abs();
+            if wrap {
+                dx = dx.min(width as i32 - dx);
+                dy = dy.min(height as i32 - dy);
+            }
+            let dist = (((dx * dx) + (dy * dy)) as f64).sqrt() as i32;
+            if dist < min_dist as i32 {
+                min_dist = dist as u32;
+            }
+        }
+
+        if min_dist >= 255 {
+            *pixel = image::Rgb([255, 255, 255]);
+        } else {
+            *pixel = image::Rgb([min_dist as u8, min_dist as u8, min_dist as u8]);
+        }
+    }
+    buffer
+}
+
+/// Index of the closest of `coords` for every pixel, row by row. Useful with
+/// [`region_borders`](crate::vector::region_borders) to trace cell borders.
+pub fn voronoi_labels(width: u32, height: u32, coords: &[(u32, u32)]) -> Vec<u32> {
+    let mut labels: Vec<u32> = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let mut closest: u32 = 0;
+            let mut min_dist: i64 = i64::MAX;
+            for (i, &(x2, y2)) in coords.iter().enumerate() {
+                let dx = x as i64 - x2 as i64;
+                let dy = y as i64 - y2 as i64;
+                if dx * dx + dy * dy < min_dist {
+                    min_dist = dx * dx + dy * dy;
+                    closest = i as u32;
+                }
+            }
+            labels.push(closest);
+        }
+    }
+    labels
+}
+
+/// Implementation of value noise, see [`value_field`].
+pub fn value(width: u32, height: u32, points_wide: u32, points_tall: u32, rng: &mut StdRng) -> Result<Buf> {
+    Ok(field_to_buf(&value_field(width, height, points_wide, points_tall, rng)?))
+}
+
+/// Value noise at full float precision, heights are 0.0..=1.0.
+///
+/// A `points_wide` x `points_tall` lattice of random values is spread over the image with its corners on
+/// the image corners and every point on a whole pixel. Pixels in between are interpolated along the lattice
+/// rows and then down every column. Both counts need to be at least 2.
+pub fn value_field(width: u32, height: u32, points_wide: u32, points_tall: u32, rng: &mut StdRng) -> Result<Field> {
+    require(points_wide >= 2 && points_tall >= 2, || format!("value noise needs at least 2 points each way, got {points_wide}x{points_tall}"))?;
+    let Some(lattice_size) = points_wide.checked_mul(points_tall) else {
+        return Err(Error::InvalidArgument(format!("a value noise lattice of {points_wide}x{points_tall} is too big")));
+    };
+    let lattice: Vec<f32> = (0..lattice_size).map(|_| rng.random::<f32>()).collect();
+    let point = |iter_x: u32, iter_y: u32| lattice[(iter_y * points_wide + iter_x) as usize];
+    let columns: Vec<(u32, f32)> = lattice_spans(width, points_wide);
+    let rows: Vec<(u32, f32)> = lattice_spans(height, points_tall);
+    let mut buffer: Field = image::ImageBuffer::new(width, height);
+    for (x, y, pixel) in buffer.enumerate_pixels_mut() {
+        let ((iter_x, across), (iter_y, down)) = (columns[x as usize], rows[y as usize]);
+        let top = linear_interpolate(0.0, point(iter_x, iter_y), 1.0, point(iter_x + 1, iter_y), across);
+        let bottom = linear_interpolate(0.0, point(iter_x, iter_y + 1), 1.0, point(iter_x + 1, iter_y + 1), across);
+        *pixel = image::Luma([linear_interpolate(0.0, top, 1.0, bottom, down)]);
+    }
+    Ok(buffer)
+}
+
+// for every pixel along one side, the lattice point before it and how far it is towards the next one
+// points land on whole pixels rounded down, the last one on the far edge, when several land on the same
+// pixel the last of them wins
+fn lattice_spans(size: u32, points: u32) -> Vec<(u32, f32)> {
+    let cell: f32 = (size as f32 - 1.0) / (points as f32 - 1.0);
+    let position = |iter: u32| if iter == points - 1 { size - 1 } else { (iter as f32 * cell) as u32 };
+    let mut iter: u32 = 0;
+    (0..size)
+        .map(|pixel| {
+            while iter + 2 < points && position(iter + 1) <= pixel {
+                iter += 1;
+            }
+            let (start, end) = (position(iter), position(iter + 1));
+            let across = if end > start { (pixel - start) as f32 / (end - start) as f32 } else { 1.0 };
+            (iter, across)
+        })
+        .collect()
+}
+
+/// 8 bit version of [`tileable_value_field`].
+pub fn tileable_value(width: u32, height: u32, period_x: u32, period_y: u32, rng: &mut StdRng) -> Result<Buf> {
+    Ok(field_to_buf(&tileable_value_field(width, height, period_x, period_y, rng)?))
+}
+
+/// Value noise that repeats every `period_x` x `period_y` lattice cells.
+///
+/// The image spans exactly one period and the lattice wraps, so the cell past the right edge
+/// is the first one again and the map lines up with copies of itself on every side.
+pub fn tileable_value_field(width: u32, height: u32, period_x: u32, period_y: u32, rng: &mut StdRng) -> Result<Field> {
+    require(period_x >= 1 && period_y >= 1, || format!("tileable value noise needs a period of at least 1 cell each way, got {period_x}x{period_y}"))?;
+    let Some(lattice_size) = period_x.checked_mul(period_y) else {
+        return Err(Error::InvalidArgument(format!("a value noise lattice of {period_x}x{period_y} is too big")));
+    };
+    let cell_width: f32 = width as f32 / period_x as f32;
+    let cell_height: f32 = height as f32 / period_y as f32;
+    let lattice: Vec<f32> = (0..lattice_size).map(|_| rng.random::<f32>()).collect();
+    let point = |iter_x: u32, iter_y: u32| lattice[((iter_y % period_y) * period_x + iter_x % period_x) as usize];
+    let mut buffer: Field = image::ImageBuffer::new(width, height);
+    for (x, y, pixel) in buffer.enumerate_pixels_mut() {
+        let grid_x = x as f32 / cell_width;
+        let grid_y = y as f32 / cell_height;
+        let iter_x = (grid_x as u32).min(period_x - 1);
+        let iter_y = (grid_y as u32).min(period_y - 1);
+        let top = linear_interpolate(0.0, point(iter_x, iter_y), 1.0, point(iter_x + 1, iter_y), grid_x - iter_x as f32);
+        let bottom = linear_interpolate(0.0, point(iter_x, iter_y + 1), 1.0, point(iter_x + 1, iter_y + 1), grid_x - iter_x as f32);
+        *pixel = image::Luma([linear_interpolate(0.0, top, 1.0, bottom, grid_y - iter_y as f32)]);
+    }
+    Ok(buffer)
+}
+
+/// 8 bit version of [`fractal_value_field`].
+pub fn fractal_value(width: u32, height: u32, points_wide: u32, points_tall: u32, inc: u32, octaves: u8, rng: &mut StdRng) -> Result<Buf> {
+    Ok(field_to_buf(&fractal_value_field(width, height, points_wide, points_tall, inc, octaves, rng)?))
+}
+
+/// Fractal (fBm) value noise. Each octave has `inc` times as many points per side and half
+/// the weight of the last, the sum is capped at 1.0. Octaves that would have more points than
+/// the image has pixels along a side are left out.
+///
+/// Octaves after the first take `points_tall` across and `points_wide` down, which only matters when they differ.
+pub fn fractal_value_field(width: u32, height: u32, points_wide: u32, points_tall: u32, inc: u32, octaves: u8, rng: &mut StdRng) -> Result<Field> {
+    let mut first: bool = true;
+    fractal_octaves(width, height, points_wide, points_tall, inc, octaves, |points_wide, points_tall| {
+        // the original version swapped the counts past the first octave, kept so existing maps don't change
+        if std::mem::take(&mut first) {
+            value_field(width, height, points_wide, points_tall, rng)
+        } else {
+            value_field(width, height, points_tall, points_wide, rng)
+        }
+    })
+}
+
+/// 8 bit version of [`tileable_fractal_value_field`].
+pub fn tileable_fractal_value(width: u32, height: u32, period_x: u32, period_y: u32, inc: u32, octaves: u8, rng: &mut StdRng) -> Result<Buf> {
+    Ok(field_to_buf(&tileable_fractal_value_field(width, height, period_x, period_y, inc, octaves, rng)?))
+}
+
+/// Fractal value noise made of [`tileable_value_field`] octaves. Every octave's period is a whole
+/// multiple of the first one, so the sum still repeats every `period_x` x `period_y` cells.
+/// Octaves whose period would be longer than the image in pixels are left out.
+pub fn tileable_fractal_value_field(width: u32, height: u32, period_x: u32, period_y: u32, inc: u32, octaves: u8, rng: &mut StdRng) -> Result<Field> {
+    fractal_octaves(width, height, period_x, period_y, inc, octaves, |period_x, period_y| tileable_value_field(width, height, period_x, period_y, rng))
+}
+
+// sums octaves from noise, each with inc times the points of the last and half the weight,
+// stopping at the first octave that would be finer than the width x height image
+fn fractal_octaves(width: u32, height: u32, mut points_wide: u32, mut points_tall: u32, inc: u32, octaves: u8, mut noise: impl FnMut(u32, u32) -> Result<Field>) -> Result<Field> {
+    let mut buffer: Field = noise(points_wide, points_tall)?;
+    let mut level: f32 = 0.25;
+    for _ in 1..octaves {
+        // an octave with more points than pixels along a side can't add detail, only memory
+        let (Some(next_wide), Some(next_tall)) = (points_wide.checked_mul(inc), points_tall.checked_mul(inc)) else { break };
+        if next_wide > width || next_tall > height {
+            break;
+        }
+        points_wide = next_wide;
+        points_tall = next_tall;
+        let octave: Field = noise(points_wide, points_tall)?;
+        for (pixel, octave_pixel) in buffer.pixels_mut().zip(octave.pixels()) {
+            pixel[0] = (pixel[0] + octave_pixel[0] * level).min(1.0);
+        }
+        level /= 2.0;
+    }
+    Ok(buffer)
+}
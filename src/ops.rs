@@ -0,0 +1,316 @@
+//! Operations on maps: masks, combining, rescaling and brightness curves.
+//!
+//! Grayscale maps keep their value in the red channel, masks are white on black.
+
+use crate::color::{BLACK, WHITE};
+use crate::error::same_size;
+use crate::morphology::{dilate, Shape, StructuringElement};
+use crate::{Buf, Error, Field, Result};
+
+// width and height of input times factor, failing when either doesn't fit in a u32
+fn upscaled_size(input: &Buf, factor: u32) -> Result<(u32, u32)> {
+    match (input.width().checked_mul(factor), input.height().checked_mul(factor)) {
+        (Some(width), Some(height)) => Ok((width, height)),
+        _ => Err(Error::InvalidArgument(format!("upscaling {}x{} by {factor} is too big", input.width(), input.height()))),
+    }
+}
+
+/// Upscales an input buffer of size width, height by a scale of `factor`, every pixel becomes a square.
+pub fn upscale_image_square(factor: u32, input: Buf) -> Result<Buf> {
+    let (output_width, output_height) = upscaled_size(&input, factor)?;
+    let mut buffer: Buf = image::ImageBuffer::new(output_width, output_height);
+    for(x, y, pixel) in input.enumerate_pixels() {
+        for sub_y in 0..factor{
+            for sub_x in 0..factor{
+                let buffer_pixel = buffer.get_pixel_mut(sub_x + (x * factor), sub_y + (y * factor));
+                *buffer_pixel = *pixel;
+            }
+        }
+    }
+    Ok(buffer)
+}
+
+/// Upscales a white on black map by `factor * 2 + 1`, drawing a plus of lines through every white pixel.
+pub fn upscale_image_lines(factor: u32, input: Buf) -> Result<Buf> {
+    let Some(scale) = factor.checked_mul(2).and_then(|double| double.checked_add(1)) else {
+        return Err(Error::InvalidArgument(format!("an upscale factor of {factor} is too big")));
+    };
+    let (output_width, output_height) = upscaled_size(&input, scale)?;
+    let mut buffer: Buf = image::ImageBuffer::new(output_width, output_height);
+    for (x, y, pixel) in input.enumerate_pixels() {
+        if pixel[0] == 255 {
+            let center_x = (x * scale) as i64;
+            let center_y = (y * scale) as i64;
+            for i in -(factor as i64)..=factor as i64 {
+                // the arms of pixels on the left and top edges hang off the image
+                if center_x + i >= 0 {
+                    let sub_pixel = buffer.get_pixel_mut((center_x + i) as u32, center_y as u32);
+                    *sub_pixel = image::Rgb(WHITE);
+                }
+                if center_y + i >= 0 {
+                    let sub_pixel2 = buffer.get_pixel_mut(center_x as u32, (center_y + i) as u32);
+                    *sub_pixel2 = image::Rgb(WHITE);
+                }
+            }
+        }
+    }
+
+    Ok(buffer)
+}
+
+/// Flips every channel, black becomes white.
+pub fn invert(input: Buf) -> Buf {
+    let mut buffer: Buf = image::ImageBuffer::new(input.width(), input.height());
+    for (x, y, pixel) in buffer.enumerate_pixels_mut() {
+        let input_pixel = input.get_pixel(x, y);
+        *pixel = image::Rgb([255 - input_pixel[0], 255 - input_pixel[1], 255 - input_pixel[2]]);
+    }
+
+    buffer
+}
+
+/// White where both masks are white.
+pub fn and(input1: Buf, input2: Buf) -> Result<Buf> {
+    same_size(input1.dimensions(), input2.dimensions())?;
+    let mut buffer: Buf = image::ImageBuffer::new(input1.width(), input1.height());
+    for (x, y, pixel) in buffer.enumerate_pixels_mut() {
+        if input1.get_pixel(x, y)[0] == 255 && input2.get_pixel(x, y)[0] == 255 {
+            *pixel = image::Rgb(WHITE);
+        } else {
+            *pixel = image::Rgb(BLACK);
+        }
+    }
+
+    Ok(buffer)
+}
+
+/// White where either mask is white.
+pub fn or(input1: Buf, input2: Buf) -> Result<Buf> {
+    same_size(input1.dimensions(), input2.dimensions())?;
+    let mut buffer: Buf = image::ImageBuffer::new(input1.width(), input1.height());
+    for (x, y, pixel) in buffer.enumerate_pixels_mut() {
+        if input1.get_pixel(x, y)[0] == 255 || input2.get_pixel(x, y)[0] == 255 {
+            *pixel = image::Rgb(WHITE);
+        } else {
+            *pixel = image::Rgb(BLACK);
+        }
+    }
+
+    Ok(buffer)
+}
+
+/// Black where a pixel is exactly `target`, white everywhere else.
+pub fn not(target: [u8; 3], input: Buf) -> Buf{
+    let mut buffer: Buf = image::ImageBuffer::new(input.width(), input.height());
+    for (x, y, pixel) in buffer.enumerate_pixels_mut() {
+        let sub_pixel = input.get_pixel(x, y);
+        if target[0] == sub_pixel[0] && target[1] == sub_pixel[1] && target[2] == sub_pixel[2] {
+            *pixel = image::Rgb(BLACK);
+        } else {
+            *pixel = image::Rgb(WHITE);
+        }
+    }
+
+    buffer
+}
+
+/// White where the first mask is white and the second isn't.
+pub fn subtract(input1: Buf, input2: Buf) -> Result<Buf> {
+    same_size(input1.dimensions(), input2.dimensions())?;
+    let mut buffer: Buf = image::ImageBuffer::new(input1.width(), input1.height());
+    for (x, y, pixel) in buffer.enumerate_pixels_mut() {
+        if input1.get_pixel(x, y)[0] == 255 && input2.get_pixel(x, y)[0] == 255 {
+            *pixel = image::Rgb(BLACK);
+        } else if input1.get_pixel(x, y)[0] == 255{
+            *pixel = image::Rgb(WHITE);
+        } else {
+            *pixel = image::Rgb(BLACK);
+        }
+    }
+
+    Ok(buffer)
+}
+
+/// Grows every pixel of exactly `color` into a square reaching `radius` pixels around it, other pixels are dropped.
+pub fn expand(radius: u32, color: [u8; 3], input: Buf) -> Buf {
+    let mut mask: Buf = invert(not(color, input));
+    mask = dilate(&mask, &StructuringElement::new(Shape::Square, radius));
+    for pixel in mask.pixels_mut() {
+        *pixel = if pixel[0] == 255 { image::Rgb(color) } else { image::Rgb(BLACK) };
+    }
+    mask
+}
+
+/// The y at `x` on the line through (x1, y1) and (x2, y2), `y1` when the two x are the same.
+pub fn linear_interpolate(x1: f32, y1: f32, x2: f32, y2: f32, x: f32) -> f32 {
+    // prevent divide by zero errors
+    if x1 == x2 {
+        return y1;
+    }
+    y1 + (x - x1) * ((y1 - y2) / (x1 - x2))
+}
+
+/// Copies every non-black pixel of `input2` on top of `input`.
+pub fn overlay(input: &mut Buf, input2: Buf) -> Result<()> {
+    same_size(input.dimensions(), input2.dimensions())?;
+    for (x, y, pixel) in input2.enumerate_pixels() {
+        if pixel[0] != 0 || pixel[1] != 0 || pixel[2] != 0 {
+            let sub_pixel = input.get_pixel_mut(x, y);
+            *sub_pixel = image::Rgb([pixel[0], pixel[1], pixel[2]]);
+        }
+        
+    }
+    Ok(())
+}
+
+/// Keeps the pixels whose red channel is within `lower..=higher`, everything else is black.
+/// The kept pixels keep their color when `preserve` is set, otherwise they become white.
+pub fn threshhold(input: &Buf, lower: u8, higher: u8, preserve: bool) -> Buf {
+    let mut buffer: Buf = image::ImageBuffer::new(input.width(), input.height());
+    for (x, y, pixel) in input.enumerate_pixels() {
+        if pixel[0] >= lower && pixel[0] <= higher {
+            if preserve {
+                let sub_pixel = buffer.get_pixel_mut(x, y);
+                *sub_pixel = image::Rgb([pixel[0], pixel[1], pixel[2]]);
+            } else {
+                let sub_pixel = buffer.get_pixel_mut(x, y);
+                *sub_pixel = image::Rgb([255, 255, 255]);
+            }
+        }
+    }
+    buffer
+}
+
+/// Stretches a grayscale map so its brightest pixel is white.
+pub fn normalize(input: &mut Buf) {
+    let mut lightest: u8 = 0;
+    for (_x, _y, pixel) in input.enumerate_pixels() {
+        if pixel[0] > lightest {
+            lightest = pixel[0];
+        }
+    }
+
+    let ratio: f32 = 255.0 / lightest as f32;
+
+    for (_x, _y, pixel) in input.enumerate_pixels_mut() {
+        let color: u16 = (pixel[0] as f32 * ratio) as u16;
+        *pixel = image::Rgb([color as u8, color as u8, color as u8]);
+    }
+}
+
+/// Multiplies every channel by `level`, capped at 255.
+pub fn scale_noise(input: &mut Buf, level: f32){
+    for (_x, _y, pixel) in input.enumerate_pixels_mut() {
+        let mut red: u32 = ((pixel[0] as f32) * level) as u32;
+        let mut green: u32 = ((pixel[1] as f32) * level) as u32;
+        let mut blue: u32 = ((pixel[2] as f32) * level) as u32;
+        if red > 255 {
+            red = 255;
+        }
+        if green > 255 {
+            green = 255;
+
+        }
+        if blue > 255 {
+            blue = 255;
+        }
+        *pixel = image::Rgb([red as u8, green as u8, blue as u8]);
+    }
+}
+
+/// Adds `level` to every channel, clamped to 0..=255.
+pub fn linear_scale_noise(input: &mut Buf, level: i32){
+    for (_x, _y, pixel) in input.enumerate_pixels_mut() {
+        let red: i32 = (pixel[0] as i32 + level).clamp(0, 255);
+        let green: i32 = (pixel[1] as i32 + level).clamp(0, 255);
+        let blue: i32 = (pixel[2] as i32 + level).clamp(0, 255);
+        *pixel = image::Rgb([red as u8, green as u8, blue as u8]);
+    }
+}
+
+/// Adds `input2` onto `input` channel by channel, capped at 255.
+pub fn add(input: &mut Buf, input2: Buf) -> Result<()> {
+    same_size(input.dimensions(), input2.dimensions())?;
+    for (x, y, pixel) in input.enumerate_pixels_mut() {
+        let second_pixel = input2.get_pixel(x, y);
+        let mut red: u32 = pixel[0] as u32 + second_pixel[0] as u32;
+        if red > 255 {
+            red = 255;
+        }
+        let mut green: u32 = pixel[1] as u32 + second_pixel[1] as u32;
+        if green > 255 {
+            green = 255;
+        }
+        let mut blue: u32 = pixel[2] as u32 + second_pixel[2] as u32;
+        if blue > 255 {
+            blue = 255;
+        }
+        *pixel = image::Rgb([red as u8, green as u8, blue as u8]);
+    }
+    Ok(())
+}
+
+/// Remaps a grayscale map so its darkest pixel becomes `lower` and its brightest `higher`,
+/// scaling everything in between along a curve rather than a straight line.
+pub fn interpolate_smoothing(input: &mut Buf, lower: u8, higher: u8){
+    let mut dim: u8 = 255;
+    let mut bright: u8 = 0;
+    for (_x, _y, pixel) in input.enumerate_pixels() {
+        if pixel[0] < dim {
+            dim = pixel[0];
+        }
+        if pixel[0] > bright {
+            bright = pixel[0];
+        }
+    }
+    let lower_ratio: f32 = if dim == 0 {
+        lower as f32
+    } else {
+        lower as f32 / dim as f32
+    };
+    let higher_ratio: f32 = if bright == 0 {
+        higher as f32
+    } else {
+        higher as f32 / bright as f32
+    };
+
+
+    for (_x, _y, pixel) in input.enumerate_pixels_mut() {
+        let color: u8 = (pixel[0] as f32 * linear_interpolate(dim as f32, lower_ratio, bright as f32, higher_ratio, pixel[0] as f32)) as u8;
+        *pixel = image::Rgb([color, color, color]);
+    }
+}
+
+/// Same curve as [`interpolate_smoothing`], with `lower` and `higher` in 0.0..=1.0.
+pub fn interpolate_smoothing_field(input: &mut Field, lower: f32, higher: f32) {
+    let mut dim: f32 = 1.0;
+    let mut bright: f32 = 0.0;
+    for pixel in input.pixels() {
+        dim = dim.min(pixel[0]);
+        bright = bright.max(pixel[0]);
+    }
+    let lower_ratio: f32 = if dim == 0.0 { lower } else { lower / dim };
+    let higher_ratio: f32 = if bright == 0.0 { higher } else { higher / bright };
+    for pixel in input.pixels_mut() {
+        pixel[0] = (pixel[0] * linear_interpolate(dim, lower_ratio, bright, higher_ratio, pixel[0])).clamp(0.0, 1.0);
+    }
+}
+
+/// Quantizes a heightfield into an 8 bit grayscale map.
+pub fn field_to_buf(input: &Field) -> Buf {
+    let mut buffer: Buf = image::ImageBuffer::new(input.width(), input.height());
+    for (pixel, height) in buffer.pixels_mut().zip(input.pixels()) {
+        let color: u8 = (height[0].clamp(0.0, 1.0) * 255.0) as u8;
+        *pixel = image::Rgb([color, color, color]);
+    }
+    buffer
+}
+
+/// Reads the red channel of an 8 bit map as a heightfield.
+pub fn buf_to_field(input: &Buf) -> Field {
+    let mut buffer: Field = image::ImageBuffer::new(input.width(), input.height());
+    for (height, pixel) in buffer.pixels_mut().zip(input.pixels()) {
+        *height = image::Luma([pixel[0] as f32 / 255.0]);
+    }
+    buffer
+}
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use image::ImageBuffer;
+
+use super::{evaluate_node, node_seed, save_layer, stable_hash, Layer, Node, Pipeline};
+use crate::{Error, Result};
+
+// bump when an operation changes its output so old cache entries stop matching
+const CACHE_VERSION: u32 = 7;
+
+// evaluates layers on demand as a graph, each layer only once per run
+// results are also kept on disk keyed by everything that went into them
+struct Evaluator<'a> {
+    pipeline: &'a Pipeline,
+    progress: &'a mut dyn FnMut(PipelineEvent),
+    seed: u64,
+    cache_dir: Option<std::path::PathBuf>,
+    keys: HashMap<String, u64>,
+    layers: HashMap<String, Layer>,
+}
+
+// the key covers the operation, its parameters, the size and seed generators use, and the keys of its inputs
+// so changing a layer only invalidates the layers downstream of it
+// keys are worked out without evaluating anything, so a cached layer never needs its inputs loaded
+fn layer_key(evaluator: &mut Evaluator, name: &str) -> Result<u64> {
+    if let Some(key) = evaluator.keys.get(name) {
+        return Ok(*key);
+    }
+    let pipeline = evaluator.pipeline;
+    let node: &Node = pipeline.nodes.iter().find(|node| node.name == name).unwrap();
+    let mut params: Vec<String> = node.params.iter().map(|(key, value)| format!("{key}={value}")).collect();
+    params.sort();
+    let mut description: String = format!("{CACHE_VERSION}|{}|{}", node.operation.name, params.join(";"));
+    if node.operation.params.contains(&"width") {
+        description.push_str(&format!("|{}x{}", evaluator.pipeline.width, evaluator.pipeline.height));
+    }
+    if node.operation.params.contains(&"seed") {
+        description.push_str(&format!("|seed {}", node_seed(node, evaluator.seed)?));
+    }
+    for input in &node.inputs {
+        description.push_str(&format!("|{:016x}", layer_key(evaluator, input)?));
+    }
+    let key: u64 = stable_hash(description.as_bytes());
+    evaluator.keys.insert(name.to_string(), key);
+    Ok(key)
+}
+
+// cache files are a kind byte, width and height, then the samples, all little endian
+fn write_cached_layer(path: &std::path::Path, layer: &Layer) -> std::io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    let (kind, width, height) = match layer {
+        Layer::Image(buffer) => (b'I', buffer.width(), buffer.height()),
+        Layer::Heights(heights) => (b'H', heights.width(), heights.height()),
+    };
+    file.write_all(&[kind])?;
+    file.write_all(&width.to_le_bytes())?;
+    file.write_all(&height.to_le_bytes())?;
+    match layer {
+        Layer::Image(buffer) => file.write_all(buffer.as_raw())?,
+        Layer::Heights(heights) => {
+            for height in heights.as_raw() {
+                file.write_all(&height.to_le_bytes())?;
+            }
+        }
+    }
+    file.flush()
+}
+
+// anything missing or malformed is treated as a cache miss
+fn read_cached_layer(path: &std::path::Path) -> Option<Layer> {
+    let bytes: Vec<u8> = std::fs::read(path).ok()?;
+    let width = u32::from_le_bytes(bytes.get(1..5)?.try_into().ok()?);
+    let height = u32::from_le_bytes(bytes.get(5..9)?.try_into().ok()?);
+    let samples: &[u8] = &bytes[9..];
+    match bytes[0] {
+        b'I' => Some(Layer::Image(ImageBuffer::from_raw(width, height, samples.to_vec())?)),
+        b'H' => {
+            let heights: Vec<f32> = samples.chunks_exact(4).map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap())).collect();
+            Some(Layer::Heights(ImageBuffer::from_raw(width, height, heights)?))
+        }
+        _ => None,
+    }
+}
+
+/// What [`run_pipeline`] is doing with a layer, for reporting progress.
+pub enum PipelineEvent<'a> {
+    /// The layer was loaded from the cache instead of evaluated.
+    Cached(&'a str),
+    /// The layer's inputs are ready and it is being evaluated.
+    Evaluating(&'a str),
+    /// The evaluated layer couldn't be written to the cache file, which only costs time on the next run.
+    CacheWriteFailed(&'a str, &'a std::path::Path, std::io::Error),
+}
+
+// makes sure name and everything it depends on are evaluated
+fn evaluate_layer(evaluator: &mut Evaluator, name: &str) -> Result<()> {
+    if evaluator.layers.contains_key(name) {
+        return Ok(());
+    }
+    let pipeline = evaluator.pipeline;
+    let node: &Node = pipeline.nodes.iter().find(|node| node.name == name).unwrap();
+    let key: u64 = layer_key(evaluator, name)?;
+    let cache_path = evaluator.cache_dir.as_ref().map(|dir| dir.join(format!("{key:016x}.layer")));
+    if let Some(layer) = cache_path.as_deref().and_then(read_cached_layer) {
+        (evaluator.progress)(PipelineEvent::Cached(name));
+        evaluator.layers.insert(name.to_string(), layer);
+        return Ok(());
+    }
+    for input in &node.inputs {
+        evaluate_layer(evaluator, input)?;
+    }
+    (evaluator.progress)(PipelineEvent::Evaluating(name));
+    let inputs: Vec<&Layer> = node.inputs.iter().map(|input| &evaluator.layers[input]).collect();
+    let layer: Layer = evaluate_node(node, &inputs, pipeline, evaluator.seed)?;
+    if let Some(path) = cache_path {
+        if let Err(error) = write_cached_layer(&path, &layer) {
+            (evaluator.progress)(PipelineEvent::CacheWriteFailed(name, &path, error));
+        }
+    }
+    evaluator.layers.insert(name.to_string(), layer);
+    Ok(())
+}
+
+/// Evaluates only the layers that are saved or dumped, dumps go to `dump_dir/<layer>.png`.
+///
+/// With a `cache_dir` every evaluated layer is also kept on disk, keyed by its operation, parameters,
+/// seed and inputs, so rerunning after a change only evaluates the layers downstream of it.
+/// `progress` hears about every layer as it is loaded or evaluated.
+pub fn run_pipeline(pipeline: &Pipeline, seed: u64, cache_dir: Option<std::path::PathBuf>, dumps: &[String], dump_dir: &str, mut progress: impl FnMut(PipelineEvent)) -> Result<()> {
+    for dump in dumps {
+        if !pipeline.nodes.iter().any(|node| &node.name == dump) {
+            return Err(Error::Pipeline(format!("can't dump unknown layer '{dump}'")));
+        }
+    }
+    if let Some(dir) = &cache_dir {
+        std::fs::create_dir_all(dir).map_err(Error::io(dir))?;
+    }
+    if !dumps.is_empty() {
+        std::fs::create_dir_all(dump_dir).map_err(Error::io(dump_dir))?;
+    }
+    let mut evaluator = Evaluator { pipeline, progress: &mut progress, seed, cache_dir, keys: HashMap::new(), layers: HashMap::new() };
+    for (layer, file) in &pipeline.saves {
+        evaluate_layer(&mut evaluator, layer)?;
+        save_layer(&evaluator.layers[layer], file)?;
+    }
+    for dump in dumps {
+        evaluate_layer(&mut evaluator, dump)?;
+        let file = std::path::Path::new(dump_dir).join(format!("{dump}.png"));
+        save_layer(&evaluator.layers[dump], &file.to_string_lossy())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,490 @@
+//! Pipeline files: a small text format that names layers, the operations that make them and their parameters.
+//!
+//! One statement per line and `#` starts a comment:
+//!
+//! ```text
+//! size <width> <height>      default size of generated layers (1024 1024)
+//! seed <number>              seed that every generator derives its own seed from
+//! <layer> = <operation> <input layers>... <param>=<value>...
+//! save <layer> <file>        .r16 / .r32 write raw heights, heights saved as .png are 16 bit
+//! ```
+//!
+//! Every layer is defined once, and only from layers defined above it, so a pipeline is always a graph
+//! without cycles. Colors are written `r,g,b` and values with spaces can be put in "double quotes".
+
+mod graph;
+
+pub use graph::{run_pipeline, PipelineEvent};
+
+use std::collections::HashMap;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::color::{create_solid_image, recolor_proportion, recolor_solid, BLACK, WHITE};
+use crate::distance::{distance_transform, signed_distance_transform};
+use crate::error::require;
+use crate::io::{save, save_16, save_raw16, save_raw32};
+use crate::morphology::{black_hat, close, dilate, erode, gradient, medial_axis, open, skeleton, top_hat, Shape, StructuringElement};
+use crate::noise::{
+    fractal_value_field, generate_noisemap_binary, generate_noisemap_bw, tileable_fractal_value_field, tileable_value_field, tileable_voronoi, value_field, voronoi,
+};
+use crate::ops::{
+    add, and, buf_to_field, expand, field_to_buf, interpolate_smoothing, interpolate_smoothing_field, invert, linear_scale_noise, normalize, not, or,
+    overlay, scale_noise, subtract, threshhold, upscale_image_lines, upscale_image_square,
+};
+use crate::regions::{fill_small_holes, flood_fill, flood_select, flood_select_field, label_regions, remove_small_regions, Connectivity};
+use crate::sim::{dla_mountain, dla_with, DlaOptions, DlaSeed};
+use crate::terrain::{aspect_map, normal_map, plan_curvature_map, profile_curvature_map, slope_map};
+use crate::world::{world_field, WorldNoise};
+use crate::{Buf, Error, Field, Result};
+
+/// A layer keeps float precision for as long as the operations on it allow.
+pub enum Layer {
+    /// 8 bit rgb map.
+    Image(Buf),
+    /// Float heightfield from the value noise generators.
+    Heights(Field),
+}
+
+/// The layer as an 8 bit map, quantizing heights if needed.
+pub fn layer_image(layer: &Layer) -> Buf {
+    match layer {
+        Layer::Image(buffer) => buffer.clone(),
+        Layer::Heights(heights) => field_to_buf(heights),
+    }
+}
+
+/// The layer as heights, reading images by their red channel.
+pub fn layer_field(layer: &Layer) -> Field {
+    match layer {
+        Layer::Image(buffer) => buf_to_field(buffer),
+        Layer::Heights(heights) => heights.clone(),
+    }
+}
+
+/// An operation the pipeline format knows, with how many layers it reads and which parameters it takes.
+pub struct Operation {
+    /// Name used in pipeline files.
+    pub name: &'static str,
+    /// Number of input layers.
+    pub inputs: usize,
+    /// Accepted parameters, all of them optional.
+    pub params: &'static [&'static str],
+}
+
+const GENERATOR_PARAMS: &[&str] = &["width", "height", "seed"];
+const MORPHOLOGY_PARAMS: &[&str] = &["shape", "radius"];
+
+/// Every operation a pipeline can use.
+pub const OPERATIONS: &[Operation] = &[
+    Operation { name: "solid", inputs: 0, params: &["width", "height", "color"] },
+    Operation { name: "noise", inputs: 0, params: GENERATOR_PARAMS },
+    Operation { name: "noise_binary", inputs: 0, params: &["width", "height", "seed", "probability"] },
+    Operation { name: "value", inputs: 0, params: &["width", "height", "seed", "points_wide", "points_tall", "tileable"] },
+    Operation { name: "fractal_value", inputs: 0, params: &["width", "height", "seed", "points_wide", "points_tall", "inc", "octaves", "tileable"] },
+    Operation { name: "voronoi", inputs: 0, params: &["width", "height", "seed", "points", "tileable"] },
+    Operation { name: "world", inputs: 0, params: &["width", "height", "seed", "chunk_x", "chunk_y", "cell_size", "inc", "octaves"] },
+    Operation { name: "dla", inputs: 0, params: &["width", "height", "seed", "ratio", "sticking", "eight_neighbours", "drift_x", "drift_y"] },
+    Operation { name: "dla_from", inputs: 1, params: &["seed", "ratio", "sticking", "eight_neighbours", "drift_x", "drift_y"] },
+    Operation { name: "dla_mountain", inputs: 0, params: &["width", "height", "seed", "levels", "ratio"] },
+    Operation { name: "invert", inputs: 1, params: &[] },
+    Operation { name: "normalize", inputs: 1, params: &[] },
+    Operation { name: "interpolate_smoothing", inputs: 1, params: &["lower", "higher"] },
+    Operation { name: "threshhold", inputs: 1, params: &["lower", "higher", "preserve"] },
+    Operation { name: "recolor_proportion", inputs: 1, params: &["red", "green", "blue"] },
+    Operation { name: "recolor_solid", inputs: 1, params: &["color"] },
+    Operation { name: "scale_noise", inputs: 1, params: &["level"] },
+    Operation { name: "linear_scale_noise", inputs: 1, params: &["level"] },
+    Operation { name: "expand", inputs: 1, params: &["radius", "color"] },
+    Operation { name: "not", inputs: 1, params: &["color"] },
+    Operation { name: "distance", inputs: 1, params: &["signed", "max"] },
+    Operation { name: "largest_region", inputs: 1, params: &["connectivity"] },
+    Operation { name: "remove_small_regions", inputs: 1, params: &["min_area", "connectivity"] },
+    Operation { name: "fill_small_holes", inputs: 1, params: &["min_area", "connectivity"] },
+    Operation { name: "flood_fill", inputs: 1, params: &["x", "y", "color", "connectivity"] },
+    Operation { name: "flood_select", inputs: 1, params: &["x", "y", "tolerance", "connectivity"] },
+    Operation { name: "dilate", inputs: 1, params: MORPHOLOGY_PARAMS },
+    Operation { name: "erode", inputs: 1, params: MORPHOLOGY_PARAMS },
+    Operation { name: "open", inputs: 1, params: MORPHOLOGY_PARAMS },
+    Operation { name: "close", inputs: 1, params: MORPHOLOGY_PARAMS },
+    Operation { name: "gradient", inputs: 1, params: MORPHOLOGY_PARAMS },
+    Operation { name: "top_hat", inputs: 1, params: MORPHOLOGY_PARAMS },
+    Operation { name: "black_hat", inputs: 1, params: MORPHOLOGY_PARAMS },
+    Operation { name: "skeleton", inputs: 1, params: &[] },
+    Operation { name: "medial_axis", inputs: 1, params: &["max"] },
+    Operation { name: "upscale_square", inputs: 1, params: &["factor"] },
+    Operation { name: "upscale_lines", inputs: 1, params: &["factor"] },
+    Operation { name: "normal_map", inputs: 1, params: &["relief", "flip_green"] },
+    Operation { name: "slope", inputs: 1, params: &["relief"] },
+    Operation { name: "aspect", inputs: 1, params: &["relief"] },
+    Operation { name: "profile_curvature", inputs: 1, params: &["relief"] },
+    Operation { name: "plan_curvature", inputs: 1, params: &["relief"] },
+    Operation { name: "overlay", inputs: 2, params: &[] },
+    Operation { name: "add", inputs: 2, params: &[] },
+    Operation { name: "and", inputs: 2, params: &[] },
+    Operation { name: "or", inputs: 2, params: &[] },
+    Operation { name: "subtract", inputs: 2, params: &[] },
+];
+
+/// One `name = operation inputs... key=value...` line of a pipeline.
+pub struct Node {
+    /// Layer name.
+    pub name: String,
+    /// Operation that makes the layer.
+    pub operation: &'static Operation,
+    /// Names of the input layers.
+    pub inputs: Vec<String>,
+    /// Parameters as written.
+    pub params: HashMap<String, String>,
+    /// Line in the file, for errors.
+    pub line: usize,
+}
+
+/// A parsed pipeline file.
+pub struct Pipeline {
+    /// Default width of generated layers.
+    pub width: u32,
+    /// Default height of generated layers.
+    pub height: u32,
+    /// Seed from the file, if it has one.
+    pub seed: Option<u64>,
+    /// Layers in the order they were defined.
+    pub nodes: Vec<Node>,
+    /// Layer name and file, in the order they were listed.
+    pub saves: Vec<(String, String)>,
+}
+
+// splits on whitespace, "double quotes" keep spaces together
+fn tokenize(line: &str) -> std::result::Result<Vec<String>, String> {
+    let mut tokens: Vec<String> = Vec::new();
+    let mut current: String = String::new();
+    let mut quoted: bool = false;
+    let mut started: bool = false;
+    for character in line.chars() {
+        match character {
+            '"' => {
+                quoted = !quoted;
+                started = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if started {
+                    tokens.push(std::mem::take(&mut current));
+                    started = false;
+                }
+            }
+            c => {
+                current.push(c);
+                started = true;
+            }
+        }
+    }
+    if quoted {
+        return Err("unclosed quote".to_string());
+    }
+    if started {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
+
+/// Parses a pipeline file, see the [module docs](self) for the format.
+pub fn parse_pipeline(text: &str) -> Result<Pipeline> {
+    let mut pipeline = Pipeline { width: 1024, height: 1024, seed: None, nodes: Vec::new(), saves: Vec::new() };
+    for (index, raw_line) in text.lines().enumerate() {
+        let line = index + 1;
+        let error = |message: String| Error::Pipeline(format!("line {line}: {message}"));
+        let content = raw_line.split('#').next().unwrap();
+        let tokens: Vec<String> = tokenize(content).map_err(error)?;
+        let parse_number = |token: Option<&String>, what: &str| -> Result<u64> {
+            token.and_then(|token| token.parse().ok()).ok_or_else(|| error(format!("{what} needs a whole number")))
+        };
+        let parse_size = |token: Option<&String>| -> Result<u32> {
+            token.and_then(|token| token.parse().ok()).ok_or_else(|| error(format!("size needs whole numbers from 0 to {}", u32::MAX)))
+        };
+        match tokens.first().map(String::as_str) {
+            None => continue,
+            Some("size") => {
+                pipeline.width = parse_size(tokens.get(1))?;
+                pipeline.height = parse_size(tokens.get(2))?;
+            }
+            Some("seed") => pipeline.seed = Some(parse_number(tokens.get(1), "seed")?),
+            Some("save") => {
+                let [_, layer, file] = &tokens[..] else {
+                    return Err(error("save needs a layer and a file".to_string()));
+                };
+                if !pipeline.nodes.iter().any(|node| &node.name == layer) {
+                    return Err(error(format!("unknown layer '{layer}'")));
+                }
+                pipeline.saves.push((layer.clone(), file.clone()));
+            }
+            Some(name) => {
+                if tokens.get(1).map(String::as_str) != Some("=") || tokens.len() < 3 {
+                    return Err(error(format!("expected '{name} = <operation> ...'")));
+                }
+                if pipeline.nodes.iter().any(|node| node.name == name) {
+                    return Err(error(format!("layer '{name}' is already defined")));
+                }
+                let Some(operation) = OPERATIONS.iter().find(|operation| operation.name == tokens[2]) else {
+                    return Err(error(format!("unknown operation '{}'", tokens[2])));
+                };
+                let mut node = Node { name: name.to_string(), operation, inputs: Vec::new(), params: HashMap::new(), line };
+                for token in &tokens[3..] {
+                    match token.split_once('=') {
+                        Some((key, value)) => {
+                            if !operation.params.contains(&key) {
+                                return Err(error(format!("{} has no parameter '{key}'", operation.name)));
+                            }
+                            node.params.insert(key.to_string(), value.to_string());
+                        }
+                        None => {
+                            if !pipeline.nodes.iter().any(|node| &node.name == token) {
+                                return Err(error(format!("unknown layer '{token}'")));
+                            }
+                            node.inputs.push(token.clone());
+                        }
+                    }
+                }
+                if node.inputs.len() != operation.inputs {
+                    return Err(error(format!("{} takes {} input layer(s), got {}", operation.name, operation.inputs, node.inputs.len())));
+                }
+                pipeline.nodes.push(node);
+            }
+        }
+    }
+    Ok(pipeline)
+}
+
+fn node_param<T: std::str::FromStr>(node: &Node, name: &str, default: T) -> Result<T> {
+    match node.params.get(name) {
+        None => Ok(default),
+        Some(value) => value.parse().map_err(|_| Error::Pipeline(format!("line {}: invalid value '{value}' for {name}", node.line))),
+    }
+}
+
+// colors are written r,g,b
+fn node_color(node: &Node, name: &str, default: [u8; 3]) -> Result<[u8; 3]> {
+    let Some(value) = node.params.get(name) else {
+        return Ok(default);
+    };
+    let channels: Vec<u8> = value.split(',').filter_map(|channel| channel.trim().parse().ok()).collect();
+    channels.try_into().map_err(|_| Error::Pipeline(format!("line {}: {name} should look like 255,255,255", node.line)))
+}
+
+fn node_element(node: &Node) -> Result<StructuringElement> {
+    Ok(StructuringElement::new(node_param(node, "shape", Shape::Disk)?, node_param(node, "radius", 1)?))
+}
+
+fn dla_options(node: &Node, seeds: Vec<DlaSeed>) -> Result<DlaOptions> {
+    Ok(DlaOptions {
+        seeds,
+        sticking: node_param(node, "sticking", 1.0)?,
+        eight_neighbours: node_param(node, "eight_neighbours", false)?,
+        drift: (node_param(node, "drift_x", 0.0)?, node_param(node, "drift_y", 0.0)?),
+    })
+}
+
+// fnv-1a, stable between runs and rust versions unlike the std hasher
+pub(crate) fn stable_hash(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+// generators get their own seed so adding a layer doesn't reshuffle the others
+pub(crate) fn node_seed(node: &Node, seed: u64) -> Result<u64> {
+    node_param(node, "seed", seed ^ stable_hash(node.name.as_bytes()))
+}
+
+// errors from the operation itself get the line of the node in front
+pub(crate) fn evaluate_node(node: &Node, inputs: &[&Layer], pipeline: &Pipeline, seed: u64) -> Result<Layer> {
+    evaluate_operation(node, inputs, pipeline, seed).map_err(|error| match error {
+        Error::Pipeline(_) => error,
+        error => Error::Pipeline(format!("line {}: {}: {error}", node.line, node.operation.name)),
+    })
+}
+
+fn evaluate_operation(node: &Node, inputs: &[&Layer], pipeline: &Pipeline, seed: u64) -> Result<Layer> {
+    let width: u32 = node_param(node, "width", pipeline.width)?;
+    let height: u32 = node_param(node, "height", pipeline.height)?;
+    let mut rng: StdRng = StdRng::seed_from_u64(node_seed(node, seed)?);
+    let relief: f32 = node_param(node, "relief", 64.0)?;
+    // tileable generators take their point counts as the period in lattice cells
+    let tileable: bool = node_param(node, "tileable", false)?;
+    let connectivity: Connectivity = node_param(node, "connectivity", Connectivity::Four)?;
+    let image = |index: usize| layer_image(inputs[index]);
+    let field = |index: usize| layer_field(inputs[index]);
+    let layer: Layer = match node.operation.name {
+        "solid" => Layer::Image(create_solid_image(width, height, node_color(node, "color", BLACK)?)),
+        "noise" => Layer::Image(generate_noisemap_bw(width, height, &mut rng)),
+        "noise_binary" => Layer::Image(generate_noisemap_binary(width, height, node_param(node, "probability", 128)?, &mut rng)),
+        "value" => {
+            let value = if tileable { tileable_value_field } else { value_field };
+            Layer::Heights(value(width, height, node_param(node, "points_wide", 9)?, node_param(node, "points_tall", 9)?, &mut rng)?)
+        }
+        "fractal_value" => {
+            let fractal_value = if tileable { tileable_fractal_value_field } else { fractal_value_field };
+            Layer::Heights(fractal_value(
+                width,
+                height,
+                node_param(node, "points_wide", 3)?,
+                node_param(node, "points_tall", 3)?,
+                node_param(node, "inc", 3)?,
+                node_param(node, "octaves", 6)?,
+                &mut rng,
+            )?)
+        }
+        "voronoi" => {
+            let voronoi = if tileable { tileable_voronoi } else { voronoi };
+            Layer::Image(voronoi(width, height, node_param(node, "points", 8)?, &mut rng)?)
+        }
+        "world" => {
+            // every world layer shares the pipeline seed, so their chunks are parts of the same world
+            let noise = WorldNoise { seed: node_param(node, "seed", seed)?, cell_size: node_param(node, "cell_size", 256.0)?, inc: node_param(node, "inc", 3)?, octaves: node_param(node, "octaves", 6)? };
+            // chunks of a world layer are width x height so they line up with the layer's other chunks
+            let x = node_param::<i64>(node, "chunk_x", 0)? as f64 * width as f64;
+            let y = node_param::<i64>(node, "chunk_y", 0)? as f64 * height as f64;
+            Layer::Heights(world_field(&noise, x, y, width, height, 1.0)?)
+        }
+        "dla" => Layer::Image(dla_with(width, height, node_param(node, "ratio", 10)?, &dla_options(node, Vec::new())?, &mut rng)?.image),
+        // grows out of the white pixels of the input
+        "dla_from" => {
+            let mask: Buf = image(0);
+            let (width, height) = mask.dimensions();
+            Layer::Image(dla_with(width, height, node_param(node, "ratio", 10)?, &dla_options(node, vec![DlaSeed::Mask(mask)])?, &mut rng)?.image)
+        }
+        "dla_mountain" => Layer::Heights(dla_mountain(width, height, node_param(node, "levels", 4)?, node_param(node, "ratio", 15)?, &mut rng)?),
+        "invert" => match inputs[0] {
+            Layer::Heights(heights) => {
+                let mut inverted: Field = heights.clone();
+                inverted.pixels_mut().for_each(|pixel| pixel[0] = 1.0 - pixel[0]);
+                Layer::Heights(inverted)
+            }
+            Layer::Image(buffer) => Layer::Image(invert(buffer.clone())),
+        },
+        "normalize" => {
+            let mut buffer: Buf = image(0);
+            normalize(&mut buffer);
+            Layer::Image(buffer)
+        }
+        "interpolate_smoothing" => {
+            let lower: u8 = node_param(node, "lower", 0)?;
+            let higher: u8 = node_param(node, "higher", 255)?;
+            match inputs[0] {
+                Layer::Heights(heights) => {
+                    let mut smoothed: Field = heights.clone();
+                    interpolate_smoothing_field(&mut smoothed, lower as f32 / 255.0, higher as f32 / 255.0);
+                    Layer::Heights(smoothed)
+                }
+                Layer::Image(buffer) => {
+                    let mut smoothed: Buf = buffer.clone();
+                    interpolate_smoothing(&mut smoothed, lower, higher);
+                    Layer::Image(smoothed)
+                }
+            }
+        }
+        "threshhold" => Layer::Image(threshhold(&image(0), node_param(node, "lower", 0)?, node_param(node, "higher", 255)?, node_param(node, "preserve", false)?)),
+        "recolor_proportion" => Layer::Image(recolor_proportion(image(0), node_param(node, "red", 255.0)?, node_param(node, "green", 255.0)?, node_param(node, "blue", 255.0)?)),
+        "recolor_solid" => {
+            let [red, green, blue] = node_color(node, "color", WHITE)?;
+            Layer::Image(recolor_solid(image(0), red, green, blue))
+        }
+        "scale_noise" => {
+            let mut buffer: Buf = image(0);
+            scale_noise(&mut buffer, node_param(node, "level", 1.0)?);
+            Layer::Image(buffer)
+        }
+        "linear_scale_noise" => {
+            let mut buffer: Buf = image(0);
+            linear_scale_noise(&mut buffer, node_param(node, "level", 0)?);
+            Layer::Image(buffer)
+        }
+        "expand" => Layer::Image(expand(node_param(node, "radius", 1)?, node_color(node, "color", WHITE)?, image(0))),
+        "not" => Layer::Image(not(node_color(node, "color", WHITE)?, image(0))),
+        // distances up to max pixels spread over the height range, signed ones put the edge at 0.5
+        "distance" => {
+            let max: f32 = node_param(node, "max", 32.0)?;
+            require(max > 0.0, || format!("max distance needs to be above 0, got {max}"))?;
+            let signed: bool = node_param(node, "signed", false)?;
+            let mut distances: Field = if signed { signed_distance_transform(&image(0)) } else { distance_transform(&image(0)) };
+            for pixel in distances.pixels_mut() {
+                pixel[0] = if signed { 0.5 + pixel[0] / max / 2.0 } else { pixel[0] / max }.clamp(0.0, 1.0);
+            }
+            Layer::Heights(distances)
+        }
+        "largest_region" => {
+            let labels = label_regions(&image(0), connectivity);
+            let largest = labels.largest().map(|region| region.label);
+            Layer::Image(labels.mask(|region| Some(region.label) == largest))
+        }
+        "remove_small_regions" => Layer::Image(remove_small_regions(&image(0), node_param(node, "min_area", 16)?, connectivity)),
+        "fill_small_holes" => Layer::Image(fill_small_holes(&image(0), node_param(node, "min_area", 16)?, connectivity)),
+        "flood_fill" => {
+            let mut buffer: Buf = image(0);
+            flood_fill(&mut buffer, node_param(node, "x", 0)?, node_param(node, "y", 0)?, node_color(node, "color", WHITE)?, connectivity)?;
+            Layer::Image(buffer)
+        }
+        // tolerance is in 8 bit steps for heights too
+        "flood_select" => {
+            let (x, y, tolerance): (u32, u32, u8) = (node_param(node, "x", 0)?, node_param(node, "y", 0)?, node_param(node, "tolerance", 0)?);
+            match inputs[0] {
+                Layer::Heights(heights) => Layer::Image(flood_select_field(heights, x, y, tolerance as f32 / 255.0, connectivity)?),
+                Layer::Image(buffer) => Layer::Image(flood_select(buffer, x, y, tolerance, connectivity)?),
+            }
+        }
+        "dilate" => Layer::Image(dilate(&image(0), &node_element(node)?)),
+        "erode" => Layer::Image(erode(&image(0), &node_element(node)?)),
+        "open" => Layer::Image(open(&image(0), &node_element(node)?)),
+        "close" => Layer::Image(close(&image(0), &node_element(node)?)),
+        "gradient" => Layer::Image(gradient(&image(0), &node_element(node)?)),
+        "top_hat" => Layer::Image(top_hat(&image(0), &node_element(node)?)),
+        "black_hat" => Layer::Image(black_hat(&image(0), &node_element(node)?)),
+        "skeleton" => Layer::Image(skeleton(&image(0))),
+        // widths up to max pixels from the middle spread over the height range
+        "medial_axis" => {
+            let max: f32 = node_param(node, "max", 32.0)?;
+            require(max > 0.0, || format!("max distance needs to be above 0, got {max}"))?;
+            let mut distances: Field = medial_axis(&image(0));
+            distances.pixels_mut().for_each(|pixel| pixel[0] = (pixel[0] / max).clamp(0.0, 1.0));
+            Layer::Heights(distances)
+        }
+        "upscale_square" => Layer::Image(upscale_image_square(node_param(node, "factor", 2)?, image(0))?),
+        "upscale_lines" => Layer::Image(upscale_image_lines(node_param(node, "factor", 1)?, image(0))?),
+        "normal_map" => Layer::Image(normal_map(&field(0), relief, node_param(node, "flip_green", false)?)),
+        "slope" => Layer::Image(slope_map(&field(0), relief)),
+        "aspect" => Layer::Image(aspect_map(&field(0), relief)),
+        "profile_curvature" => Layer::Image(profile_curvature_map(&field(0), relief)),
+        "plan_curvature" => Layer::Image(plan_curvature_map(&field(0), relief)),
+        "overlay" | "add" | "and" | "or" | "subtract" => {
+            let (mut first, second) = (image(0), image(1));
+            match node.operation.name {
+                "overlay" => overlay(&mut first, second)?,
+                "add" => add(&mut first, second)?,
+                "and" => first = and(first, second)?,
+                "or" => first = or(first, second)?,
+                _ => first = subtract(first, second)?,
+            }
+            Layer::Image(first)
+        }
+        name => unreachable!("operation {name} is listed but not evaluated"),
+    };
+    Ok(layer)
+}
+
+/// Saves a layer by file extension: .r16 / .raw and .r32 write raw heights,
+/// anything else is an image and heights become 16 bit.
+pub fn save_layer(layer: &Layer, file: &str) -> Result<()> {
+    if file.ends_with(".r16") || file.ends_with(".raw") {
+        save_raw16(file, &layer_field(layer), false)
+    } else if file.ends_with(".r32") {
+        save_raw32(file, &layer_field(layer), false)
+    } else {
+        match layer {
+            Layer::Heights(heights) => save_16(file, heights),
+            Layer::Image(buffer) => save(file, buffer),
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Whole planets: noise sampled on the surface of a sphere and projected into flat maps.
+//!
+//! Heights come from 3D value noise at points on the unit sphere, so there is no pinching at the poles and
+//! no seam where the map wraps around. Points are (x, y, z) with y towards the north pole, longitude 0 along
+//! +x and longitude 90° east along +z. The noise settings are a [`WorldNoise`] with its cell size in planet radii.
+
+use std::f64::consts::{FRAC_PI_2, PI};
+
+use crate::error::require;
+use crate::world::{hash_to_unit, mix, octave_key, WorldNoise};
+use crate::{Buf, Field, Result};
+
+// random 0.0..1.0 value of a 3d lattice point
+fn lattice_value(seed: u64, octave: u8, x: i64, y: i64, z: i64) -> f32 {
+    hash_to_unit(mix(
+        octave_key(seed, octave)
+            ^ (x as u64).wrapping_mul(0x9e3779b97f4a7c15)
+            ^ (y as u64).wrapping_mul(0xc2b2ae3d27d4eb4f)
+            ^ (z as u64).wrapping_mul(0x165667b19e3779f9),
+    ))
+}
+
+// trilinear value noise of one octave at a position in lattice cells
+fn octave_value(seed: u64, octave: u8, point: [f64; 3]) -> f32 {
+    let cell = point.map(f64::floor);
+    let [x, y, z] = cell.map(|c| c as i64);
+    let [across, up, deep] = [0, 1, 2].map(|axis| (point[axis] - cell[axis]) as f32);
+    let mut height: f32 = 0.0;
+    for corner in 0..8 {
+        let (dx, dy, dz) = (corner & 1, (corner >> 1) & 1, (corner >> 2) & 1);
+        let weight = if dx == 1 { across } else { 1.0 - across } * if dy == 1 { up } else { 1.0 - up } * if dz == 1 { deep } else { 1.0 - deep };
+        height += lattice_value(seed, octave, x + dx, y + dy, z + dz) * weight;
+    }
+    height
+}
+
+/// Height of the planet at a point on the unit sphere, 0.0..=1.0, octaves weighted like
+/// [`world_height`](crate::world::world_height).
+pub fn planet_height(noise: &WorldNoise, point: [f64; 3]) -> f32 {
+    let mut cell_size: f64 = noise.cell_size;
+    let mut height: f32 = octave_value(noise.seed, 0, point.map(|c| c / cell_size));
+    let mut total: f32 = 1.0;
+    let mut level: f32 = 0.25;
+    for octave in 1..noise.octaves.max(1) {
+        cell_size /= noise.inc as f64;
+        height += octave_value(noise.seed, octave, point.map(|c| c / cell_size)) * level;
+        total += level;
+        level /= 2.0;
+    }
+    height / total
+}
+
+/// The point on the unit sphere at a longitude and latitude in radians.
+pub fn sphere_point(longitude: f64, latitude: f64) -> [f64; 3] {
+    [latitude.cos() * longitude.cos(), latitude.sin(), latitude.cos() * longitude.sin()]
+}
+
+/// Longitude and latitude in radians of a point on the unit sphere.
+pub fn longitude_latitude(point: [f64; 3]) -> (f64, f64) {
+    (point[2].atan2(point[0]), point[1].clamp(-1.0, 1.0).asin())
+}
+
+pub(crate) fn check_noise(noise: &WorldNoise) -> Result<()> {
+    require(noise.cell_size > 0.0, || format!("planet cells need a size above 0, got {}", noise.cell_size))?;
+    require(noise.inc >= 1, || "planet octaves need an inc of at least 1".to_string())
+}
+
+/// Equirectangular map of the whole planet, longitude -180° to 180° left to right and the north pole at the top.
+/// Use a width twice the height for square pixels.
+pub fn equirectangular_field(noise: &WorldNoise, width: u32, height: u32) -> Result<Field> {
+    check_noise(noise)?;
+    let mut buffer: Field = image::ImageBuffer::new(width, height);
+    for (x, y, pixel) in buffer.enumerate_pixels_mut() {
+        let longitude = (x as f64 + 0.5) / width as f64 * 2.0 * PI - PI;
+        let latitude = FRAC_PI_2 - (y as f64 + 0.5) / height as f64 * PI;
+        *pixel = image::Luma([planet_height(noise, sphere_point(longitude, latitude))]);
+    }
+    Ok(buffer)
+}
+
+/// Names of the cube map faces in the order [`cube_map_fields`] returns them.
+pub const CUBE_FACES: [&str; 6] = ["px", "nx", "py", "ny", "pz", "nz"];
+
+// direction through pixel (s, t) in -1.0..=1.0 of a face, laid out like opengl cube maps
+fn cube_direction(face: usize, s: f64, t: f64) -> [f64; 3] {
+    let direction = match face {
+        0 => [1.0, -t, -s],
+        1 => [-1.0, -t, s],
+        2 => [s, 1.0, t],
+        3 => [s, -1.0, -t],
+        4 => [s, -t, 1.0],
+        _ => [-s, -t, -1.0],
+    };
+    let length = direction.iter().map(|c| c * c).sum::<f64>().sqrt();
+    direction.map(|c| c / length)
+}
+
+/// The six `size` x `size` faces of a cube map of the planet, +x, -x, +y, -y, +z, -z like OpenGL
+/// and most engines expect. Neighbouring faces meet without seams.
+pub fn cube_map_fields(noise: &WorldNoise, size: u32) -> Result<[Field; 6]> {
+    check_noise(noise)?;
+    Ok(std::array::from_fn(|face| {
+        let mut buffer: Field = image::ImageBuffer::new(size, size);
+        for (x, y, pixel) in buffer.enumerate_pixels_mut() {
+            let s = (x as f64 + 0.5) / size as f64 * 2.0 - 1.0;
+            let t = (y as f64 + 0.5) / size as f64 * 2.0 - 1.0;
+            *pixel = image::Luma([planet_height(noise, cube_direction(face, s, t))]);
+        }
+        buffer
+    }))
+}
+
+// the sphere point seen at (u, v) in -1.0..=1.0 of an orthographic view, None off the globe
+// the view looks at longitude, latitude with north up
+fn orthographic_point(u: f64, v: f64, longitude: f64, latitude: f64) -> Option<[f64; 3]> {
+    let depth_squared = 1.0 - u * u - v * v;
+    if depth_squared < 0.0 {
+        return None;
+    }
+    let center = sphere_point(longitude, latitude);
+    let east = [-longitude.sin(), 0.0, longitude.cos()];
+    let north = sphere_point(longitude, latitude + FRAC_PI_2);
+    let depth = depth_squared.sqrt();
+    Some([0, 1, 2].map(|axis| center[axis] * depth + east[axis] * u + north[axis] * v))
+}
+
+/// The globe as seen from far away above `longitude`, `latitude` (radians), filling a `size` x `size` image.
+/// Pixels off the globe are 0.0, see [`clip_to_globe`] for blacking them out after coloring.
+pub fn orthographic_field(noise: &WorldNoise, size: u32, longitude: f64, latitude: f64) -> Result<Field> {
+    check_noise(noise)?;
+    let mut buffer: Field = image::ImageBuffer::new(size, size);
+    for (x, y, pixel) in buffer.enumerate_pixels_mut() {
+        let u = (x as f64 + 0.5) / size as f64 * 2.0 - 1.0;
+        let v = 1.0 - (y as f64 + 0.5) / size as f64 * 2.0;
+        if let Some(point) = orthographic_point(u, v, longitude, latitude) {
+            *pixel = image::Luma([planet_height(noise, point)]);
+        }
+    }
+    Ok(buffer)
+}
+
+/// Paints everything outside the circle an orthographic globe fills black.
+pub fn clip_to_globe(input: &mut Buf) {
+    let size = input.width().min(input.height()) as f64;
+    for (x, y, pixel) in input.enumerate_pixels_mut() {
+        let u = (x as f64 + 0.5) / size * 2.0 - 1.0;
+        let v = (y as f64 + 0.5) / size * 2.0 - 1.0;
+        if u * u + v * v > 1.0 {
+            *pixel = image::Rgb([0, 0, 0]);
+        }
+    }
+}
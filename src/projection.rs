@@ -0,0 +1,257 @@
+//! World map projections and resampling maps from one projection to another.
+//!
+//! Maps cover the whole sphere with longitude -180° to 180° and the north pole up. Positions on a map are
+//! (u, v) in -1.0..=1.0 from the left and bottom edges to the right and top, whatever the pixel size.
+
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+use std::str::FromStr;
+
+use crate::error::require;
+use crate::planet::{check_noise, planet_height, sphere_point};
+use crate::world::WorldNoise;
+use crate::{Buf, Error, Field, Result};
+
+/// A projection of the whole sphere onto a flat map.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Projection {
+    /// Longitude and latitude straight onto x and y, twice as wide as tall.
+    Equirectangular,
+    /// Conformal cylinder, square and cut off at about 85° north and south like web maps.
+    Mercator,
+    /// Equal area ellipse, twice as wide as tall.
+    Mollweide,
+    /// Distances from the north pole are true, the south pole is the outer circle. Square.
+    AzimuthalEquidistant,
+}
+
+impl FromStr for Projection {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Projection> {
+        match name {
+            "equirectangular" => Ok(Projection::Equirectangular),
+            "mercator" => Ok(Projection::Mercator),
+            "mollweide" => Ok(Projection::Mollweide),
+            "azimuthal-equidistant" => Ok(Projection::AzimuthalEquidistant),
+            _ => Err(Error::InvalidArgument(format!("unknown projection '{name}'"))),
+        }
+    }
+}
+
+/// How pixels are read between pixel centers.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Resampling {
+    /// Weighted average of the 4 closest pixels.
+    Bilinear,
+    /// Catmull-Rom spline through the 16 closest pixels, sharper than bilinear.
+    Bicubic,
+}
+
+impl FromStr for Resampling {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Resampling> {
+        match name {
+            "bilinear" => Ok(Resampling::Bilinear),
+            "bicubic" => Ok(Resampling::Bicubic),
+            _ => Err(Error::InvalidArgument(format!("unknown resampling '{name}'"))),
+        }
+    }
+}
+
+impl Projection {
+    /// Width over height of a map in this projection.
+    pub fn aspect(self) -> f64 {
+        match self {
+            Projection::Equirectangular | Projection::Mollweide => 2.0,
+            Projection::Mercator | Projection::AzimuthalEquidistant => 1.0,
+        }
+    }
+
+    /// Where longitude, latitude (radians) lands on the map, None when the projection leaves it out.
+    pub fn forward(self, longitude: f64, latitude: f64) -> Option<(f64, f64)> {
+        match self {
+            Projection::Equirectangular => Some((longitude / PI, latitude / FRAC_PI_2)),
+            Projection::Mercator => {
+                let v = (FRAC_PI_4 + latitude / 2.0).tan().ln() / PI;
+                (v.abs() <= 1.0).then_some((longitude / PI, v))
+            }
+            Projection::Mollweide => {
+                // solve 2t + sin 2t = pi sin latitude with newton's method
+                let target = PI * latitude.sin();
+                let mut theta = latitude;
+                for _ in 0..20 {
+                    let slope = 2.0 + 2.0 * (2.0 * theta).cos();
+                    if slope.abs() < 1e-12 {
+                        break;
+                    }
+                    theta -= (2.0 * theta + (2.0 * theta).sin() - target) / slope;
+                }
+                Some((longitude / PI * theta.cos(), theta.sin()))
+            }
+            Projection::AzimuthalEquidistant => {
+                let radius = (FRAC_PI_2 - latitude) / PI;
+                Some((radius * longitude.sin(), -radius * longitude.cos()))
+            }
+        }
+    }
+
+    /// The longitude, latitude (radians) at a map position, None off the map.
+    pub fn inverse(self, u: f64, v: f64) -> Option<(f64, f64)> {
+        if u.abs() > 1.0 || v.abs() > 1.0 {
+            return None;
+        }
+        match self {
+            Projection::Equirectangular => Some((u * PI, v * FRAC_PI_2)),
+            Projection::Mercator => Some((u * PI, 2.0 * (v * PI).exp().atan() - FRAC_PI_2)),
+            Projection::Mollweide => {
+                let theta = v.asin();
+                let longitude = u * PI / theta.cos();
+                if longitude.abs() > PI {
+                    return None;
+                }
+                Some((longitude, ((2.0 * theta + (2.0 * theta).sin()) / PI).clamp(-1.0, 1.0).asin()))
+            }
+            Projection::AzimuthalEquidistant => {
+                let radius = (u * u + v * v).sqrt();
+                (radius <= 1.0).then(|| (u.atan2(-v), FRAC_PI_2 - radius * PI))
+            }
+        }
+    }
+
+    // cylinders wrap around at the left and right edges
+    fn wraps(self) -> bool {
+        matches!(self, Projection::Equirectangular | Projection::Mercator)
+    }
+}
+
+// map position of the center of pixel (x, y) and back
+fn pixel_position(x: u32, y: u32, width: u32, height: u32) -> (f64, f64) {
+    ((x as f64 + 0.5) / width as f64 * 2.0 - 1.0, 1.0 - (y as f64 + 0.5) / height as f64 * 2.0)
+}
+
+fn position_pixel(u: f64, v: f64, width: u32, height: u32) -> (f64, f64) {
+    ((u + 1.0) / 2.0 * width as f64 - 0.5, (1.0 - v) / 2.0 * height as f64 - 0.5)
+}
+
+fn cubic(p0: f32, p1: f32, p2: f32, p3: f32, t: f32) -> f32 {
+    p1 + 0.5 * t * (p2 - p0 + t * (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3 + t * (3.0 * (p1 - p2) + p3 - p0)))
+}
+
+// reads channel at a fractional pixel position, edges clamp or wrap around horizontally
+fn sample(channel: &impl Fn(u32, u32) -> f32, width: u32, height: u32, x: f64, y: f64, wrap: bool, resampling: Resampling) -> f32 {
+    let column = |i: i64| if wrap { i.rem_euclid(width as i64) as u32 } else { i.clamp(0, width as i64 - 1) as u32 };
+    let row = |i: i64| i.clamp(0, height as i64 - 1) as u32;
+    let (left, top) = (x.floor() as i64, y.floor() as i64);
+    let (across, down) = ((x - x.floor()) as f32, (y - y.floor()) as f32);
+    match resampling {
+        Resampling::Bilinear => {
+            let upper = channel(column(left), row(top)) * (1.0 - across) + channel(column(left + 1), row(top)) * across;
+            let lower = channel(column(left), row(top + 1)) * (1.0 - across) + channel(column(left + 1), row(top + 1)) * across;
+            upper * (1.0 - down) + lower * down
+        }
+        Resampling::Bicubic => {
+            let rows: [f32; 4] = std::array::from_fn(|j| {
+                let y = row(top - 1 + j as i64);
+                let [a, b, c, d] = std::array::from_fn(|i| channel(column(left - 1 + i as i64), y));
+                cubic(a, b, c, d, across)
+            });
+            cubic(rows[0], rows[1], rows[2], rows[3], down)
+        }
+    }
+}
+
+// source pixel position for every output pixel, row by row, None where the output is off the map
+fn source_pixels(from: Projection, to: Projection, source: (u32, u32), width: u32, height: u32) -> Vec<Option<(f64, f64)>> {
+    let mut positions: Vec<Option<(f64, f64)>> = Vec::with_capacity(width as usize * height as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let (u, v) = pixel_position(x, y, width, height);
+            let position = to.inverse(u, v)
+                .and_then(|(longitude, latitude)| from.forward(longitude, latitude))
+                .map(|(u, v)| position_pixel(u, v, source.0, source.1));
+            positions.push(position);
+        }
+    }
+    positions
+}
+
+/// Resamples a heightfield in the `from` projection into a `width` x `height` map in the `to` projection.
+/// Pixels off the new map are 0.0.
+pub fn reproject_field(input: &Field, from: Projection, to: Projection, width: u32, height: u32, resampling: Resampling) -> Result<Field> {
+    require(input.width() > 0 && input.height() > 0, || "can't reproject an empty map".to_string())?;
+    let channel = |x: u32, y: u32| input.get_pixel(x, y)[0];
+    let positions = source_pixels(from, to, input.dimensions(), width, height);
+    let mut buffer: Field = image::ImageBuffer::new(width, height);
+    for (pixel, position) in buffer.pixels_mut().zip(positions) {
+        if let Some((x, y)) = position {
+            *pixel = image::Luma([sample(&channel, input.width(), input.height(), x, y, from.wraps(), resampling)]);
+        }
+    }
+    Ok(buffer)
+}
+
+/// [`reproject_field`] for a colored map, every channel is resampled on its own. Pixels off the new map are black.
+pub fn reproject_image(input: &Buf, from: Projection, to: Projection, width: u32, height: u32, resampling: Resampling) -> Result<Buf> {
+    require(input.width() > 0 && input.height() > 0, || "can't reproject an empty map".to_string())?;
+    let positions = source_pixels(from, to, input.dimensions(), width, height);
+    let mut buffer: Buf = image::ImageBuffer::new(width, height);
+    for (pixel, position) in buffer.pixels_mut().zip(positions) {
+        if let Some((x, y)) = position {
+            *pixel = image::Rgb(std::array::from_fn(|c| {
+                let channel = |x: u32, y: u32| input.get_pixel(x, y)[c] as f32;
+                sample(&channel, input.width(), input.height(), x, y, from.wraps(), resampling).round().clamp(0.0, 255.0) as u8
+            }));
+        }
+    }
+    Ok(buffer)
+}
+
+/// A planet sampled straight into a map in any projection, without resampling a flat map in between.
+/// Pixels off the map are 0.0.
+pub fn planet_map_field(noise: &WorldNoise, projection: Projection, width: u32, height: u32) -> Result<Field> {
+    check_noise(noise)?;
+    let mut buffer: Field = image::ImageBuffer::new(width, height);
+    for (x, y, pixel) in buffer.enumerate_pixels_mut() {
+        let (u, v) = pixel_position(x, y, width, height);
+        if let Some((longitude, latitude)) = projection.inverse(u, v) {
+            *pixel = image::Luma([planet_height(noise, sphere_point(longitude, latitude))]);
+        }
+    }
+    Ok(buffer)
+}
+
+/// Paints everything a map in `projection` doesn't cover black, like the corners around a Mollweide ellipse.
+pub fn clip_to_projection(input: &mut Buf, projection: Projection) {
+    let (width, height) = input.dimensions();
+    for (x, y, pixel) in input.enumerate_pixels_mut() {
+        let (u, v) = pixel_position(x, y, width, height);
+        if projection.inverse(u, v).is_none() {
+            *pixel = image::Rgb([0, 0, 0]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forward_then_inverse_round_trips() {
+        let projections = [Projection::Equirectangular, Projection::Mercator, Projection::Mollweide, Projection::AzimuthalEquidistant];
+        for projection in projections {
+            // the poles have no longitude and mercator stops short of them
+            for latitude in (-80..=80).step_by(10) {
+                for longitude in (-170..=170).step_by(17) {
+                    let (longitude, latitude) = ((longitude as f64).to_radians(), (latitude as f64).to_radians());
+                    let (u, v) = projection.forward(longitude, latitude).unwrap();
+                    let (back_longitude, back_latitude) = projection.inverse(u, v).unwrap();
+                    assert!(
+                        (back_longitude - longitude).abs() < 1e-9 && (back_latitude - latitude).abs() < 1e-9,
+                        "{projection:?} at {longitude}, {latitude} came back as {back_longitude}, {back_latitude}"
+                    );
+                }
+            }
+        }
+    }
+}
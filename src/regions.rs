@@ -0,0 +1,289 @@
+//! Connected regions of masks: islands, lakes and whatever else a threshold splits off.
+//!
+//! White pixels are those with a red channel of 255, like the masks [`threshhold`](crate::ops::threshhold) makes.
+
+use std::str::FromStr;
+
+use crate::color::{BLACK, WHITE};
+use crate::error::require;
+use crate::{Buf, Error, Field, Result};
+
+/// Which neighbouring pixels count as connected.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Connectivity {
+    /// Only pixels sharing an edge, diagonal lines fall apart.
+    Four,
+    /// Diagonal pixels too.
+    Eight,
+}
+
+impl FromStr for Connectivity {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Connectivity> {
+        match name {
+            "4" => Ok(Connectivity::Four),
+            "8" => Ok(Connectivity::Eight),
+            _ => Err(Error::InvalidArgument(format!("connectivity is 4 or 8, got '{name}'"))),
+        }
+    }
+}
+
+impl Connectivity {
+    fn offsets(self) -> &'static [(i64, i64)] {
+        match self {
+            Connectivity::Four => &[(0, -1), (-1, 0), (1, 0), (0, 1)],
+            Connectivity::Eight => &[(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)],
+        }
+    }
+}
+
+/// One connected region and its statistics.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Region {
+    /// Label of the region's pixels in [`Labels::labels`].
+    pub label: u32,
+    /// Number of pixels.
+    pub area: usize,
+    /// Smallest box holding the region, (left, top, right, bottom) with right and bottom included.
+    pub bounds: (u32, u32, u32, u32),
+    /// Mean of the pixel centers, a pixel (x, y) covers x..x+1 and y..y+1.
+    pub centroid: (f64, f64),
+    /// Pixel edges between the region and anything else, the image border and holes included.
+    pub perimeter: usize,
+}
+
+/// Every pixel of a mask labeled with the region it belongs to.
+pub struct Labels {
+    /// Width of the mask.
+    pub width: u32,
+    /// Height of the mask.
+    pub height: u32,
+    /// Label of every pixel row by row, 0 for black pixels and `n` for the pixels of `regions[n - 1]`.
+    pub labels: Vec<u32>,
+    /// Regions in the order their first pixel comes row by row.
+    pub regions: Vec<Region>,
+}
+
+impl Labels {
+    /// Label of pixel (`x`, `y`), 0 when it is black.
+    pub fn label(&self, x: u32, y: u32) -> u32 {
+        self.labels[y as usize * self.width as usize + x as usize]
+    }
+
+    /// The region pixel (`x`, `y`) belongs to, None when it is black.
+    pub fn region(&self, x: u32, y: u32) -> Option<&Region> {
+        self.label(x, y).checked_sub(1).map(|index| &self.regions[index as usize])
+    }
+
+    /// The region with the most pixels, the first of them on a tie.
+    pub fn largest(&self) -> Option<&Region> {
+        self.regions.iter().rev().max_by_key(|region| region.area)
+    }
+
+    /// White on black mask of the regions `keep` returns true for.
+    pub fn mask(&self, keep: impl Fn(&Region) -> bool) -> Buf {
+        let kept: Vec<bool> = self.regions.iter().map(keep).collect();
+        let mut buffer: Buf = image::ImageBuffer::new(self.width, self.height);
+        for (pixel, &label) in buffer.pixels_mut().zip(&self.labels) {
+            let white = label != 0 && kept[label as usize - 1];
+            *pixel = image::Rgb(if white { WHITE } else { BLACK });
+        }
+        buffer
+    }
+}
+
+/// Splits the white pixels of `mask` into connected regions and measures each of them.
+pub fn label_regions(mask: &Buf, connectivity: Connectivity) -> Labels {
+    let (width, height) = mask.dimensions();
+    let index = |x: u32, y: u32| y as usize * width as usize + x as usize;
+    let mut labels: Vec<u32> = vec![0; width as usize * height as usize];
+    let mut regions: Vec<Region> = Vec::new();
+    let mut stack: Vec<(u32, u32)> = Vec::new();
+    for (start_x, start_y, start) in mask.enumerate_pixels() {
+        if start[0] != 255 || labels[index(start_x, start_y)] != 0 {
+            continue;
+        }
+        // flood the region from its first pixel
+        let label = regions.len() as u32 + 1;
+        let mut region = Region { label, area: 0, bounds: (start_x, start_y, start_x, start_y), centroid: (0.0, 0.0), perimeter: 0 };
+        let (mut sum_x, mut sum_y) = (0.0, 0.0);
+        labels[index(start_x, start_y)] = label;
+        stack.push((start_x, start_y));
+        while let Some((x, y)) = stack.pop() {
+            region.area += 1;
+            region.bounds = (region.bounds.0.min(x), region.bounds.1.min(y), region.bounds.2.max(x), region.bounds.3.max(y));
+            sum_x += x as f64 + 0.5;
+            sum_y += y as f64 + 0.5;
+            for &(dx, dy) in Connectivity::Four.offsets() {
+                let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+                if nx < 0 || ny < 0 || nx >= width as i64 || ny >= height as i64 || mask.get_pixel(nx as u32, ny as u32)[0] != 255 {
+                    region.perimeter += 1;
+                }
+            }
+            for &(dx, dy) in connectivity.offsets() {
+                let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+                if nx < 0 || ny < 0 || nx >= width as i64 || ny >= height as i64 {
+                    continue;
+                }
+                let (nx, ny) = (nx as u32, ny as u32);
+                if mask.get_pixel(nx, ny)[0] == 255 && labels[index(nx, ny)] == 0 {
+                    labels[index(nx, ny)] = label;
+                    stack.push((nx, ny));
+                }
+            }
+        }
+        region.centroid = (sum_x / region.area as f64, sum_y / region.area as f64);
+        regions.push(region);
+    }
+    Labels { width, height, labels, regions }
+}
+
+// every pixel connected to (x, y) through pixels that pass `matches`, the start pixel included
+// callers check (x, y) is inside before reading it
+fn flood(width: u32, height: u32, x: u32, y: u32, connectivity: Connectivity, matches: impl Fn(u32, u32) -> bool) -> Vec<bool> {
+    let index = |x: u32, y: u32| y as usize * width as usize + x as usize;
+    let mut filled: Vec<bool> = vec![false; width as usize * height as usize];
+    let mut stack: Vec<(u32, u32)> = vec![(x, y)];
+    filled[index(x, y)] = true;
+    while let Some((x, y)) = stack.pop() {
+        for &(dx, dy) in connectivity.offsets() {
+            let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+            if nx < 0 || ny < 0 || nx >= width as i64 || ny >= height as i64 {
+                continue;
+            }
+            let (nx, ny) = (nx as u32, ny as u32);
+            if !filled[index(nx, ny)] && matches(nx, ny) {
+                filled[index(nx, ny)] = true;
+                stack.push((nx, ny));
+            }
+        }
+    }
+    filled
+}
+
+fn filled_mask(width: u32, height: u32, filled: &[bool]) -> Buf {
+    let mut buffer: Buf = image::ImageBuffer::new(width, height);
+    for (pixel, &white) in buffer.pixels_mut().zip(filled) {
+        *pixel = image::Rgb(if white { WHITE } else { BLACK });
+    }
+    buffer
+}
+
+/// Paints `color` over the area of exactly the same color as pixel (`x`, `y`) that it is connected to,
+/// like a paint bucket. Returns how many pixels were painted.
+pub fn flood_fill(input: &mut Buf, x: u32, y: u32, color: [u8; 3], connectivity: Connectivity) -> Result<usize> {
+    let (width, height) = input.dimensions();
+    require(x < width && y < height, || format!("flood start {x}, {y} is outside the {width}x{height} image"))?;
+    let start = *input.get_pixel(x, y);
+    let filled = flood(width, height, x, y, connectivity, |nx, ny| *input.get_pixel(nx, ny) == start);
+    let mut count: usize = 0;
+    for (pixel, &fill) in input.pixels_mut().zip(&filled) {
+        if fill {
+            *pixel = image::Rgb(color);
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// White on black mask of the area connected to pixel (`x`, `y`) whose red channel is within `tolerance`
+/// of that pixel's, like a basin or plateau around a point of a grayscale heightmap.
+pub fn flood_select(input: &Buf, x: u32, y: u32, tolerance: u8, connectivity: Connectivity) -> Result<Buf> {
+    let (width, height) = input.dimensions();
+    require(x < width && y < height, || format!("flood start {x}, {y} is outside the {width}x{height} image"))?;
+    let start = input.get_pixel(x, y)[0];
+    let filled = flood(width, height, x, y, connectivity, |nx, ny| input.get_pixel(nx, ny)[0].abs_diff(start) <= tolerance);
+    Ok(filled_mask(width, height, &filled))
+}
+
+/// [`flood_select`] on a heightfield, with `tolerance` in heights.
+pub fn flood_select_field(input: &Field, x: u32, y: u32, tolerance: f32, connectivity: Connectivity) -> Result<Buf> {
+    let (width, height) = input.dimensions();
+    require(x < width && y < height, || format!("flood start {x}, {y} is outside the {width}x{height} image"))?;
+    let start = input.get_pixel(x, y)[0];
+    let filled = flood(width, height, x, y, connectivity, |nx, ny| (input.get_pixel(nx, ny)[0] - start).abs() <= tolerance);
+    Ok(filled_mask(width, height, &filled))
+}
+
+/// Blacks out the white regions of `mask` smaller than `min_area` pixels, like the single pixel islands
+/// thresholding noise leaves along a coast.
+pub fn remove_small_regions(mask: &Buf, min_area: usize, connectivity: Connectivity) -> Buf {
+    label_regions(mask, connectivity).mask(|region| region.area >= min_area)
+}
+
+/// Whitens the black regions of `mask` smaller than `min_area` pixels, like the single pixel lakes
+/// thresholding noise leaves inland. Holes are connected the other way from the white regions around them,
+/// with 4 connected land a diagonal gap is part of a hole. Black regions touching the image border may
+/// go on past it, like a bay cut off by the edge of the map, so they are never filled.
+pub fn fill_small_holes(mask: &Buf, min_area: usize, connectivity: Connectivity) -> Buf {
+    let hole_connectivity = match connectivity {
+        Connectivity::Four => Connectivity::Eight,
+        Connectivity::Eight => Connectivity::Four,
+    };
+    let mut holes: Buf = mask.clone();
+    for pixel in holes.pixels_mut() {
+        *pixel = image::Rgb(if pixel[0] == 255 { BLACK } else { WHITE });
+    }
+    let labels = label_regions(&holes, hole_connectivity);
+    let (width, height) = mask.dimensions();
+    let fill: Vec<bool> = labels
+        .regions
+        .iter()
+        .map(|region| {
+            let (left, top, right, bottom) = region.bounds;
+            let on_border = left == 0 || top == 0 || right + 1 == width || bottom + 1 == height;
+            region.area < min_area && !on_border
+        })
+        .collect();
+    let mut buffer: Buf = mask.clone();
+    for (pixel, &label) in buffer.pixels_mut().zip(&labels.labels) {
+        if label != 0 && fill[label as usize - 1] {
+            *pixel = image::Rgb(WHITE);
+        }
+    }
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mask(width: u32, height: u32, white: &[(u32, u32)]) -> Buf {
+        image::ImageBuffer::from_fn(width, height, |x, y| image::Rgb(if white.contains(&(x, y)) { WHITE } else { BLACK }))
+    }
+
+    #[test]
+    fn rectangle_statistics() {
+        let rectangle: Vec<(u32, u32)> = (1..=2).flat_map(|y| (2..=4).map(move |x| (x, y))).collect();
+        for connectivity in [Connectivity::Four, Connectivity::Eight] {
+            let labels = label_regions(&mask(8, 6, &rectangle), connectivity);
+            assert_eq!(labels.regions, vec![Region { label: 1, area: 6, bounds: (2, 1, 4, 2), centroid: (3.5, 2.0), perimeter: 10 }]);
+        }
+    }
+
+    #[test]
+    fn l_shape_statistics() {
+        // an L with a pixel touching the end of its foot diagonally
+        let shape = [(1, 1), (1, 2), (1, 3), (2, 3), (3, 3), (4, 4)];
+        let four = label_regions(&mask(6, 6, &shape), Connectivity::Four);
+        assert_eq!(four.regions.len(), 2);
+        assert_eq!(four.regions[0], Region { label: 1, area: 5, bounds: (1, 1, 3, 3), centroid: (2.1, 2.9), perimeter: 12 });
+        assert_eq!(four.regions[1], Region { label: 2, area: 1, bounds: (4, 4, 4, 4), centroid: (4.5, 4.5), perimeter: 4 });
+        let eight = label_regions(&mask(6, 6, &shape), Connectivity::Eight);
+        assert_eq!(eight.regions.len(), 1);
+        let region = &eight.regions[0];
+        assert_eq!((region.area, region.bounds, region.perimeter), (6, (1, 1, 4, 4), 16));
+        assert!((region.centroid.0 - 2.5).abs() < 1e-9 && (region.centroid.1 - 19.0 / 6.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn fill_small_holes_leaves_border_lakes() {
+        let water = [(0, 3), (0, 4), (4, 4)];
+        let land: Vec<(u32, u32)> = (0..8).flat_map(|y| (0..8).map(move |x| (x, y))).filter(|pixel| !water.contains(pixel)).collect();
+        let filled = fill_small_holes(&mask(8, 8, &land), 4, Connectivity::Four);
+        assert_eq!(filled.get_pixel(4, 4).0, WHITE);
+        assert_eq!(filled.get_pixel(0, 3).0, BLACK);
+        assert_eq!(filled.get_pixel(0, 4).0, BLACK);
+    }
+}
@@ -0,0 +1,528 @@
+//! Simulations that grow maps step by step.
+
+use image::imageops::{self, FilterType};
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use crate::color::create_solid_image;
+use crate::error::require;
+use crate::vector::Path;
+use crate::{Buf, Error, Field, Result};
+
+// walkers further than this from the cluster in the distance field take big steps
+const DISTANCE_CAP: i64 = 16;
+// gap between the cluster radius and the circle walkers spawn on
+const SPAWN_GAP: f64 = 5.0;
+
+/// Something a dla cluster grows from.
+pub enum DlaSeed {
+    /// A single pixel.
+    Point(u32, u32),
+    /// Every pixel on the line between two pixels, for growing ridges off a spine.
+    Line((u32, u32), (u32, u32)),
+    /// Every white pixel of a mask the size of the image, like a coastline or an earlier cluster.
+    Mask(Buf),
+}
+
+/// Settings for [`dla_with`], [`DlaOptions::default`] is the plain [`dla`].
+pub struct DlaOptions {
+    /// What the cluster grows from, the center pixel when empty.
+    pub seeds: Vec<DlaSeed>,
+    /// Chance (0.0..=1.0) that a walker sticks when it touches the cluster, lower values grow denser clumps.
+    pub sticking: f32,
+    /// Diagonal neighbours count as touching too.
+    pub eight_neighbours: bool,
+    /// Pull on every step in x and y, each -1.0..=1.0 and together at most 1.0 in size.
+    /// (0.0, 0.5) sends every other step down, which grows the cluster upward like lightning from the ground.
+    /// Drifting walkers start on the edges they drift away from and are replaced when they reach the opposite ones.
+    pub drift: (f32, f32),
+}
+
+impl Default for DlaOptions {
+    fn default() -> DlaOptions {
+        DlaOptions { seeds: Vec::new(), sticking: 1.0, eight_neighbours: false, drift: (0.0, 0.0) }
+    }
+}
+
+/// A grown dla cluster along with how it grew.
+pub struct DlaGrowth {
+    /// The cluster, white on black.
+    pub image: Buf,
+    /// Every pixel of the cluster in the order it stuck, seeds first.
+    pub order: Vec<(u32, u32)>,
+    /// For every pixel in `order`, the index in `order` of the neighbour it stuck to, None for seeds.
+    pub parents: Vec<Option<usize>>,
+}
+
+impl DlaGrowth {
+    /// The tree as (parent, child) edges between indices in `order`, in the order the children stuck.
+    pub fn edges(&self) -> Vec<(usize, usize)> {
+        self.parents.iter().enumerate().filter_map(|(child, parent)| parent.map(|parent| (parent, child))).collect()
+    }
+
+    /// For every pixel in `order`, how many pixels hang off it, itself included.
+    /// Large on trunks near the seeds and 1 on the tips of branches.
+    pub fn descendants(&self) -> Vec<usize> {
+        let mut counts: Vec<usize> = vec![1; self.order.len()];
+        // children always stick after their parent, so going backwards sees every child first
+        for child in (0..self.order.len()).rev() {
+            if let Some(parent) = self.parents[child] {
+                counts[parent] += counts[child];
+            }
+        }
+        counts
+    }
+
+    /// The cluster as it was after the first `count` pixels stuck, for animating the growth.
+    pub fn image_at(&self, count: usize) -> Buf {
+        let mut buffer: Buf = create_solid_image(self.image.width(), self.image.height(), [0, 0, 0]);
+        for &(x, y) in self.order.iter().take(count) {
+            *buffer.get_pixel_mut(x, y) = image::Rgb([255, 255, 255]);
+        }
+        buffer
+    }
+
+    /// Every pixel of the cluster colored by when it stuck, blending from `first` for the seeds to `last` for the final pixel.
+    pub fn age_image(&self, first: [u8; 3], last: [u8; 3]) -> Buf {
+        let mut buffer: Buf = create_solid_image(self.image.width(), self.image.height(), [0, 0, 0]);
+        let newest = self.order.len().saturating_sub(1).max(1) as f32;
+        for (age, &(x, y)) in self.order.iter().enumerate() {
+            let t = age as f32 / newest;
+            *buffer.get_pixel_mut(x, y) = image::Rgb(std::array::from_fn(|c| (first[c] as f32 + (last[c] as f32 - first[c] as f32) * t).round() as u8));
+        }
+        buffer
+    }
+
+    /// The cluster drawn with every pixel as a disk sized by its [`descendants`](DlaGrowth::descendants),
+    /// up to `max_radius` pixels for the pixel carrying the most, so trunks come out thick and tips thin.
+    pub fn trunk_image(&self, max_radius: f32) -> Buf {
+        let (width, height) = self.image.dimensions();
+        let mut buffer: Buf = create_solid_image(width, height, [0, 0, 0]);
+        let descendants = self.descendants();
+        let most = descendants.iter().copied().max().unwrap_or(1) as f32;
+        for (&(x, y), &count) in self.order.iter().zip(&descendants) {
+            // cube root like the area of branches in river networks and trees
+            let radius = max_radius * (count as f32 / most).cbrt();
+            let reach = radius.floor() as i64;
+            for dy in -reach..=reach {
+                for dx in -reach..=reach {
+                    let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+                    if (dx * dx + dy * dy) as f32 <= radius * radius && nx >= 0 && ny >= 0 && nx < width as i64 && ny < height as i64 {
+                        *buffer.get_pixel_mut(nx as u32, ny as u32) = image::Rgb([255, 255, 255]);
+                    }
+                }
+            }
+        }
+        buffer
+    }
+
+    /// The tree as polylines through pixel centers. Each path follows the child with the most descendants
+    /// down to a tip, side branches start on the pixel they split off from.
+    pub fn branches(&self) -> Vec<Path> {
+        let descendants = self.descendants();
+        // the heaviest child of every pixel carries its path on
+        let mut heaviest: Vec<Option<usize>> = vec![None; self.order.len()];
+        for (parent, child) in self.edges() {
+            if heaviest[parent].is_none_or(|current| descendants[child] > descendants[current]) {
+                heaviest[parent] = Some(child);
+            }
+        }
+        let center = |index: usize| (self.order[index].0 as f32 + 0.5, self.order[index].1 as f32 + 0.5);
+        let mut paths: Vec<Path> = Vec::new();
+        for start in 0..self.order.len() {
+            let mut path: Path = match self.parents[start] {
+                None => vec![center(start)],
+                Some(parent) if heaviest[parent] != Some(start) => vec![center(parent), center(start)],
+                Some(_) => continue,
+            };
+            let mut current = start;
+            while let Some(child) = heaviest[current] {
+                path.push(center(child));
+                current = child;
+            }
+            if path.len() > 1 {
+                paths.push(path);
+            }
+        }
+        paths
+    }
+}
+
+// every pixel on the line from start to end
+fn line_pixels(start: (u32, u32), end: (u32, u32)) -> Vec<(u32, u32)> {
+    let steps = (start.0 as i64 - end.0 as i64).abs().max((start.1 as i64 - end.1 as i64).abs()).max(1);
+    (0..=steps)
+        .map(|i| {
+            let t = i as f64 / steps as f64;
+            let x = start.0 as f64 + (end.0 as f64 - start.0 as f64) * t;
+            let y = start.1 as f64 + (end.1 as f64 - start.1 as f64) * t;
+            (x.round() as u32, y.round() as u32)
+        })
+        .collect()
+}
+
+// the cluster as it grows plus what walkers need to move fast around it
+struct Cluster<'a> {
+    options: &'a DlaOptions,
+    width: u32,
+    height: u32,
+    stuck: Vec<bool>,
+    // index of the pixel each stuck pixel attached to, None for seeds
+    parent: Vec<Option<usize>>,
+    // stuck pixels in the order they stuck
+    order: Vec<usize>,
+    // lower bound on the distance to the closest stuck pixel, capped at DISTANCE_CAP
+    distance: Vec<u8>,
+    center: (f64, f64),
+    radius: f64,
+    count: usize,
+}
+
+impl Cluster<'_> {
+    // center is the middle of the seeds, the spawn circle grows around it
+    fn new(options: &DlaOptions, width: u32, height: u32, center: (f64, f64)) -> Cluster<'_> {
+        let size = width as usize * height as usize;
+        Cluster {
+            options,
+            width,
+            height,
+            stuck: vec![false; size],
+            parent: vec![None; size],
+            order: Vec::new(),
+            distance: vec![DISTANCE_CAP as u8; size],
+            center,
+            radius: 0.0,
+            count: 0,
+        }
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        y as usize * self.width as usize + x as usize
+    }
+
+    fn is_stuck(&self, x: u32, y: u32) -> bool {
+        self.stuck[self.index(x, y)]
+    }
+
+    fn stick(&mut self, x: u32, y: u32, parent: Option<(u32, u32)>) {
+        let index = self.index(x, y);
+        self.stuck[index] = true;
+        self.parent[index] = parent.map(|(parent_x, parent_y)| self.index(parent_x, parent_y));
+        self.order.push(index);
+        self.count += 1;
+        self.radius = self.radius.max(((x as f64 - self.center.0).powi(2) + (y as f64 - self.center.1).powi(2)).sqrt());
+        // only the pixels around the new one can get closer to the cluster
+        for dy in -DISTANCE_CAP..=DISTANCE_CAP {
+            for dx in -DISTANCE_CAP..=DISTANCE_CAP {
+                let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+                if nx < 0 || ny < 0 || nx >= self.width as i64 || ny >= self.height as i64 {
+                    continue;
+                }
+                let distance = ((dx * dx + dy * dy) as f64).sqrt().min(DISTANCE_CAP as f64) as u8;
+                let index = self.index(nx as u32, ny as u32);
+                self.distance[index] = self.distance[index].min(distance);
+            }
+        }
+    }
+
+    // a neighbour of (x, y) that is part of the cluster, straight ones first
+    fn neighbour(&self, x: u32, y: u32) -> Option<(u32, u32)> {
+        let neighbours: &[(i64, i64)] = if self.options.eight_neighbours {
+            &[(0, -1), (0, 1), (1, 0), (-1, 0), (-1, -1), (1, -1), (-1, 1), (1, 1)]
+        } else {
+            &[(0, -1), (0, 1), (1, 0), (-1, 0)]
+        };
+        neighbours.iter().map(|&(dx, dy)| (x as i64 + dx, y as i64 + dy)).find_map(|(nx, ny)| {
+            let inside = nx >= 0 && ny >= 0 && nx < self.width as i64 && ny < self.height as i64;
+            (inside && self.is_stuck(nx as u32, ny as u32)).then_some((nx as u32, ny as u32))
+        })
+    }
+
+    fn distance_from_center(&self, x: u32, y: u32) -> f64 {
+        ((x as f64 - self.center.0).powi(2) + (y as f64 - self.center.1).powi(2)).sqrt()
+    }
+
+    fn drifts(&self) -> bool {
+        self.options.drift != (0.0, 0.0)
+    }
+
+    // true on an edge the drift pushes walkers against
+    fn downstream(&self, x: u32, y: u32) -> bool {
+        let (drift_x, drift_y) = self.options.drift;
+        (drift_x > 0.0 && x == self.width - 1) || (drift_x < 0.0 && x == 0) || (drift_y > 0.0 && y == self.height - 1) || (drift_y < 0.0 && y == 0)
+    }
+
+    // a free pixel on the circle just outside the cluster, or anywhere free once that circle leaves the image
+    // drifting walkers start on the edges they drift away from instead, so they flow over the whole cluster
+    fn spawn(&self, rng: &mut StdRng) -> (u32, u32) {
+        let (drift_x, drift_y) = self.options.drift;
+        let radius = self.radius + SPAWN_GAP;
+        for _ in 0..32 {
+            if self.drifts() {
+                let (mut x, mut y) = (rng.random::<u32>() % self.width, rng.random::<u32>() % self.height);
+                if rng.random::<f32>() * (drift_x.abs() + drift_y.abs()) < drift_x.abs() {
+                    x = if drift_x > 0.0 { 0 } else { self.width - 1 };
+                } else {
+                    y = if drift_y > 0.0 { 0 } else { self.height - 1 };
+                }
+                if !self.is_stuck(x, y) {
+                    return (x, y);
+                }
+                continue;
+            }
+            let angle: f64 = rng.random::<f64>() * std::f64::consts::TAU;
+            let x = (self.center.0 + radius * angle.cos()).round();
+            let y = (self.center.1 + radius * angle.sin()).round();
+            if x >= 0.0 && y >= 0.0 && x < self.width as f64 && y < self.height as f64 && !self.is_stuck(x as u32, y as u32) {
+                return (x as u32, y as u32);
+            }
+        }
+        loop {
+            let x: u32 = rng.random::<u32>() % self.width;
+            let y: u32 = rng.random::<u32>() % self.height;
+            if !self.is_stuck(x, y) {
+                return (x, y);
+            }
+        }
+    }
+
+    // the random spread and drift offset of a jump that stays within room pixels
+    // n steps spread a walker about sqrt(n) pixels while the drift carries it n times its pull,
+    // so a drifting jump takes as many steps as fit both into room
+    fn jump(&self, room: f64) -> (f64, (f64, f64)) {
+        let (drift_x, drift_y) = (self.options.drift.0 as f64, self.options.drift.1 as f64);
+        if !self.drifts() {
+            return (room, (0.0, 0.0));
+        }
+        // steps the drift doesn't pick are the random ones
+        let spread = (1.0 - drift_x.abs() - drift_y.abs()).max(0.0).sqrt();
+        let pull = drift_x.hypot(drift_y);
+        let root_steps = (-spread + (spread * spread + 4.0 * pull * room).sqrt()) / (2.0 * pull);
+        let steps = root_steps * root_steps;
+        (spread * root_steps, (drift_x * steps, drift_y * steps))
+    }
+
+    // walks one walker until it sticks, returns where and the pixel it stuck to
+    fn walk(&self, rng: &mut StdRng) -> ((u32, u32), (u32, u32)) {
+        let (mut x, mut y) = self.spawn(rng);
+        let (drift_x, drift_y) = self.options.drift;
+        loop {
+            if let Some(parent) = self.neighbour(x, y) {
+                if self.options.sticking >= 1.0 || rng.random::<f32>() < self.options.sticking {
+                    return ((x, y), parent);
+                }
+            }
+            // walkers that wander far off or drift past the cluster are replaced by a new one
+            let from_center = self.distance_from_center(x, y);
+            let far = !self.drifts() && from_center > self.radius * 2.0 + 32.0 && self.radius + SPAWN_GAP < (self.width.min(self.height) / 2) as f64;
+            if far || self.downstream(x, y) {
+                (x, y) = self.spawn(rng);
+                continue;
+            }
+            // jump as far as the cluster is known to be away, a random walk leaves that circle at a random point
+            let clear = (self.distance[self.index(x, y)] as f64).max(from_center - self.radius - 1.0);
+            if clear >= 4.0 {
+                let angle: f64 = rng.random::<f64>() * std::f64::consts::TAU;
+                let (step, shift) = self.jump(clear - 2.0);
+                x = (x as f64 + step * angle.cos() + shift.0).round().clamp(0.0, self.width as f64 - 1.0) as u32;
+                y = (y as f64 + step * angle.sin() + shift.1).round().clamp(0.0, self.height as f64 - 1.0) as u32;
+                continue;
+            }
+            // close to the cluster it is the plain one pixel walk, unless the drift picks the step
+            let pull: f32 = rng.random();
+            let direction: u8 = if pull < drift_x.abs() {
+                if drift_x > 0.0 { 1 } else { 3 }
+            } else if pull < drift_x.abs() + drift_y.abs() {
+                if drift_y > 0.0 { 2 } else { 0 }
+            } else {
+                rng.random::<u8>() % 4
+            };
+            let (mut next_x, mut next_y) = (x, y);
+            if direction == 0 && y != 0 {
+                next_y -= 1;
+            } else if direction == 1 && x != self.width - 1 {
+                next_x += 1;
+            } else if direction == 2 && y != self.height - 1 {
+                next_y += 1;
+            } else if direction == 3 && x != 0 {
+                next_x -= 1;
+            }
+            // walkers that didn't stick can't step into the cluster
+            if !self.is_stuck(next_x, next_y) {
+                (x, y) = (next_x, next_y);
+            }
+        }
+    }
+
+    // adds walkers until the cluster has target pixels, never the whole image so there is always room to walk
+    fn grow(&mut self, target: usize, rng: &mut StdRng) {
+        let target = target.min(self.width as usize * self.height as usize - 1);
+        while self.count < target {
+            let ((x, y), parent) = self.walk(rng);
+            self.stick(x, y, Some(parent));
+        }
+    }
+
+    fn position(&self, index: usize) -> (u32, u32) {
+        (index as u32 % self.width, index as u32 / self.width)
+    }
+
+    fn growth(&self) -> DlaGrowth {
+        let order: Vec<(u32, u32)> = self.order.iter().map(|&index| self.position(index)).collect();
+        let mut age: Vec<usize> = vec![0; self.stuck.len()];
+        for (stuck, &index) in self.order.iter().enumerate() {
+            age[index] = stuck;
+        }
+        let parents = self.order.iter().map(|&index| self.parent[index].map(|parent| age[parent])).collect();
+        let mut image: Buf = create_solid_image(self.width, self.height, [0, 0, 0]);
+        for &(x, y) in &order {
+            *image.get_pixel_mut(x, y) = image::Rgb([255, 255, 255]);
+        }
+        DlaGrowth { image, order, parents }
+    }
+}
+
+// pixels making up ratio percent of an image
+fn ratio_target(width: u32, height: u32, ratio: u32) -> usize {
+    ((width as u64 * height as u64 * ratio as u64).div_ceil(100) as usize).max(1)
+}
+
+/// Diffusion limited aggregation: random walkers stick to a cluster grown from the center
+/// until `ratio` percent of the image is white.
+///
+/// Walkers start on a circle just outside the cluster, are replaced when they wander far away
+/// and cross open space in big jumps, so only the last few pixels of every walk are one pixel steps.
+///
+/// The returned [`DlaGrowth`] also records the order pixels stuck in and what they stuck to.
+pub fn dla(output_width: u32, output_height: u32, ratio: u32, rng: &mut StdRng) -> Result<DlaGrowth> {
+    dla_with(output_width, output_height, ratio, &DlaOptions::default(), rng)
+}
+
+/// [`dla`] grown from any seeds, with a sticking chance, diagonal contact and a drift.
+/// The seed pixels count towards `ratio`.
+pub fn dla_with(output_width: u32, output_height: u32, ratio: u32, options: &DlaOptions, rng: &mut StdRng) -> Result<DlaGrowth> {
+    require(output_width >= 2 && output_height >= 2, || format!("dla needs an image of at least 2x2, got {output_width}x{output_height}"))?;
+    require(ratio <= 100, || format!("dla ratio is a percentage, got {ratio}"))?;
+    require(options.sticking > 0.0 && options.sticking <= 1.0, || format!("dla sticking is a chance above 0 and up to 1, got {}", options.sticking))?;
+    let (drift_x, drift_y) = options.drift;
+    require(drift_x.abs() + drift_y.abs() <= 1.0, || format!("dla drift can be at most 1 in size, got {drift_x}, {drift_y}"))?;
+    let width: u32 = output_width;
+    let height: u32 = output_height;
+
+    // starting coordinates are right around the center unless there are seeds
+    let mut seed_pixels: Vec<(u32, u32)> = Vec::new();
+    for seed in &options.seeds {
+        match seed {
+            DlaSeed::Point(x, y) => seed_pixels.push((*x, *y)),
+            DlaSeed::Line(start, end) => seed_pixels.extend(line_pixels(*start, *end)),
+            DlaSeed::Mask(mask) => {
+                require(mask.dimensions() == (width, height), || format!("dla seed masks need to be {width}x{height}, got {}x{}", mask.width(), mask.height()))?;
+                seed_pixels.extend(mask.enumerate_pixels().filter(|(_, _, pixel)| pixel[0] == 255).map(|(x, y, _)| (x, y)));
+            }
+        }
+    }
+    if let Some(&(x, y)) = seed_pixels.iter().find(|&&(x, y)| x >= width || y >= height) {
+        return Err(Error::InvalidArgument(format!("dla seed {x}, {y} is outside the {width}x{height} image")));
+    }
+    if seed_pixels.is_empty() {
+        seed_pixels.push((width / 2, height / 2));
+    }
+    let (min_x, max_x) = seed_pixels.iter().fold((u32::MAX, 0), |(low, high), &(x, _)| (low.min(x), high.max(x)));
+    let (min_y, max_y) = seed_pixels.iter().fold((u32::MAX, 0), |(low, high), &(_, y)| (low.min(y), high.max(y)));
+    let center = ((min_x + max_x) as f64 / 2.0, (min_y + max_y) as f64 / 2.0);
+
+    let mut cluster = Cluster::new(options, width, height, center);
+    for (x, y) in seed_pixels {
+        if !cluster.is_stuck(x, y) {
+            cluster.stick(x, y, None);
+        }
+    }
+    cluster.grow(ratio_target(width, height, ratio), rng);
+    Ok(cluster.growth())
+}
+
+// the heights of a tree, 1.0 at the roots down to near 0.0 at the pixels furthest along their branches
+fn tree_heights(cluster: &Cluster) -> Field {
+    let mut depth: Vec<u32> = vec![0; cluster.stuck.len()];
+    for &index in &cluster.order {
+        depth[index] = cluster.parent[index].map_or(0, |parent| depth[parent] + 1);
+    }
+    let deepest = cluster.order.iter().map(|&index| depth[index]).max().unwrap_or(0);
+    let mut buffer: Field = image::ImageBuffer::new(cluster.width, cluster.height);
+    for &index in &cluster.order {
+        let height = 1.0 - depth[index] as f32 / (deepest + 1) as f32;
+        let (x, y) = cluster.position(index);
+        *buffer.get_pixel_mut(x, y) = image::Luma([height]);
+    }
+    buffer
+}
+
+// the tree scaled up to width x height, every pixel joined to its parent through a jittered midpoint
+fn upscale_tree<'a>(cluster: &Cluster, options: &'a DlaOptions, width: u32, height: u32, rng: &mut StdRng) -> Cluster<'a> {
+    let scale = |index: usize| -> (i64, i64) {
+        let (x, y) = (index as u64 % cluster.width as u64, index as u64 / cluster.width as u64);
+        ((x * width as u64 / cluster.width as u64) as i64, (y * height as u64 / cluster.height as u64) as i64)
+    };
+    let center = (cluster.center.0 * width as f64 / cluster.width as f64, cluster.center.1 * height as f64 / cluster.height as f64);
+    let mut upscaled = Cluster::new(options, width, height, center);
+    let clamp = |(x, y): (i64, i64)| (x.clamp(0, width as i64 - 1) as u32, y.clamp(0, height as i64 - 1) as u32);
+    for &index in &cluster.order {
+        let child = clamp(scale(index));
+        let Some(parent) = cluster.parent[index] else {
+            if !upscaled.is_stuck(child.0, child.1) {
+                upscaled.stick(child.0, child.1, None);
+            }
+            continue;
+        };
+        let parent = clamp(scale(parent));
+        let jitter = |rng: &mut StdRng| rng.random::<u32>() as i64 % 3 - 1;
+        let middle = clamp(((parent.0 as i64 + child.0 as i64) / 2 + jitter(rng), (parent.1 as i64 + child.1 as i64) / 2 + jitter(rng)));
+        // parents come first in the order, so the path starts on the tree
+        let mut previous = parent;
+        for pixel in line_pixels(parent, middle).into_iter().skip(1).chain(line_pixels(middle, child).into_iter().skip(1)) {
+            if !upscaled.is_stuck(pixel.0, pixel.1) {
+                upscaled.stick(pixel.0, pixel.1, Some(previous));
+            }
+            previous = pixel;
+        }
+    }
+    upscaled
+}
+
+/// Mountain heightfield from a dla tree: ridges follow the branches and fall away from the peak at the root,
+/// with smooth slopes in between. Heights are 0.0..=1.0.
+///
+/// The tree starts `levels` halvings smaller than the output and grows until `ratio` percent of it is covered.
+/// Every level it is scaled up twice, each pixel joined to its parent through a jittered midpoint, and grown to
+/// `ratio` percent again for finer branches. The heights of each level's tree, highest at the root and lower the
+/// further along a branch, are blurred and added onto the blurred and scaled up sum of the levels before.
+pub fn dla_mountain(width: u32, height: u32, levels: u8, ratio: u32, rng: &mut StdRng) -> Result<Field> {
+    require(levels < 16, || format!("dla mountains can have up to 15 levels, got {levels}"))?;
+    let (start_width, start_height) = (width >> levels, height >> levels);
+    require(start_width >= 8 && start_height >= 8, || {
+        format!("dla mountains need at least 8x8 pixels at the smallest level, {width}x{height} is {start_width}x{start_height} after {levels} levels")
+    })?;
+    require((1..=100).contains(&ratio), || format!("dla ratio is a percentage above 0, got {ratio}"))?;
+    let options = DlaOptions::default();
+    let mut cluster = Cluster::new(&options, start_width, start_height, ((start_width / 2) as f64, (start_height / 2) as f64));
+    cluster.stick(start_width / 2, start_height / 2, None);
+    cluster.grow(ratio_target(start_width, start_height, ratio), rng);
+    let mut total: Field = imageops::blur(&tree_heights(&cluster), 1.0);
+    // finer levels add less so the big shape of the mountain stays
+    let mut weight: f32 = 1.0;
+    for level in (0..levels).rev() {
+        let (level_width, level_height) = (width >> level, height >> level);
+        cluster = upscale_tree(&cluster, &options, level_width, level_height, rng);
+        cluster.grow(ratio_target(level_width, level_height, ratio), rng);
+        total = imageops::blur(&imageops::resize(&total, level_width, level_height, FilterType::Triangle), 1.0);
+        weight /= 2.0;
+        for (pixel, detail) in total.pixels_mut().zip(imageops::blur(&tree_heights(&cluster), 1.0).pixels()) {
+            pixel[0] += detail[0] * weight;
+        }
+    }
+    let highest = total.pixels().map(|pixel| pixel[0]).fold(0.0, f32::max);
+    if highest > 0.0 {
+        total.pixels_mut().for_each(|pixel| pixel[0] /= highest);
+    }
+    Ok(total)
+}
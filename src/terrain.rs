@@ -0,0 +1,114 @@
+//! Layers derived from a heightfield for engines: normals, slope, aspect and curvature.
+//!
+//! `height_scale` is how many pixels tall a height of 1.0 is, so it sets the steepness relative to pixel spacing.
+//! Heights are read at full float precision, so gentle slopes don't come out as 8 bit terraces.
+
+use crate::color::BLACK;
+use crate::{Buf, Field};
+
+// height of the pixel at x, y where a height of 1.0 is height_scale pixels tall
+// coordinates outside the image are clamped to the nearest edge
+fn height_at(input: &Field, height_scale: f32, x: i64, y: i64) -> f32 {
+    let x = x.clamp(0, input.width() as i64 - 1) as u32;
+    let y = y.clamp(0, input.height() as i64 - 1) as u32;
+    input.get_pixel(x, y)[0] * height_scale
+}
+
+// central difference gradient, y grows downward like the image does
+fn height_gradient(input: &Field, height_scale: f32, x: u32, y: u32) -> (f32, f32) {
+    let (x, y) = (x as i64, y as i64);
+    let dx = (height_at(input, height_scale, x + 1, y) - height_at(input, height_scale, x - 1, y)) / 2.0;
+    let dy = (height_at(input, height_scale, x, y + 1) - height_at(input, height_scale, x, y - 1)) / 2.0;
+    (dx, dy)
+}
+
+/// Tangent space normal map with each component packed into 0..255.
+/// Green points up the image (OpenGL style), `flip_green` gives the DirectX convention.
+pub fn normal_map(input: &Field, height_scale: f32, flip_green: bool) -> Buf {
+    let mut buffer: Buf = image::ImageBuffer::new(input.width(), input.height());
+    for (x, y, pixel) in buffer.enumerate_pixels_mut() {
+        let (dx, dy) = height_gradient(input, height_scale, x, y);
+        let length = (dx * dx + dy * dy + 1.0).sqrt();
+        let normal_x = -dx / length;
+        let mut normal_y = dy / length;
+        let normal_z = 1.0 / length;
+        if flip_green {
+            normal_y = -normal_y;
+        }
+        let encode = |n: f32| ((n * 0.5 + 0.5) * 255.0).round() as u8;
+        *pixel = image::Rgb([encode(normal_x), encode(normal_y), encode(normal_z)]);
+    }
+    buffer
+}
+
+/// Steepness of every pixel in whole degrees, 0 is flat and 90 is a cliff.
+pub fn slope_map(input: &Field, height_scale: f32) -> Buf {
+    let mut buffer: Buf = image::ImageBuffer::new(input.width(), input.height());
+    for (x, y, pixel) in buffer.enumerate_pixels_mut() {
+        let (dx, dy) = height_gradient(input, height_scale, x, y);
+        let degrees = (dx * dx + dy * dy).sqrt().atan().to_degrees().round() as u8;
+        *pixel = image::Rgb([degrees, degrees, degrees]);
+    }
+    buffer
+}
+
+/// Compass direction the downhill side faces, clockwise from the top of the image.
+/// 0..360 degrees is packed into 0..255, flat pixels are left at 0.
+pub fn aspect_map(input: &Field, height_scale: f32) -> Buf {
+    let mut buffer: Buf = image::ImageBuffer::new(input.width(), input.height());
+    for (x, y, pixel) in buffer.enumerate_pixels_mut() {
+        let (dx, dy) = height_gradient(input, height_scale, x, y);
+        if dx == 0.0 && dy == 0.0 {
+            *pixel = image::Rgb(BLACK);
+            continue;
+        }
+        let bearing = (-dx).atan2(dy).to_degrees().rem_euclid(360.0);
+        let value = (bearing / 360.0 * 256.0).min(255.0) as u8;
+        *pixel = image::Rgb([value, value, value]);
+    }
+    buffer
+}
+
+/// Curvature along the slope direction, positive (brighter than 128) is concave.
+/// Scaled so the strongest curvature in the map reaches 0 or 255.
+pub fn profile_curvature_map(input: &Field, height_scale: f32) -> Buf {
+    curvature_map(input, height_scale, |p, q, r, s, t| {
+        -(p * p * r + 2.0 * p * q * s + q * q * t) / ((p * p + q * q) * (1.0 + p * p + q * q).powf(1.5))
+    })
+}
+
+/// Curvature across the slope direction, positive (brighter than 128) is converging.
+/// Scaled so the strongest curvature in the map reaches 0 or 255.
+pub fn plan_curvature_map(input: &Field, height_scale: f32) -> Buf {
+    curvature_map(input, height_scale, |p, q, r, s, t| {
+        -(q * q * r - 2.0 * p * q * s + p * p * t) / (p * p + q * q).powf(1.5)
+    })
+}
+
+// evaluates a curvature formula from the first (p, q) and second (r, s, t) derivatives
+// the result is centered on 128 and scaled so the strongest curvature reaches 0 or 255
+fn curvature_map(input: &Field, height_scale: f32, formula: impl Fn(f32, f32, f32, f32, f32) -> f32) -> Buf {
+    let mut values: Vec<f32> = Vec::with_capacity((input.width() * input.height()) as usize);
+    let mut strongest: f32 = 0.0;
+    for y in 0..input.height() as i64 {
+        for x in 0..input.width() as i64 {
+            let h = |ox: i64, oy: i64| height_at(input, height_scale, x + ox, y + oy);
+            let p = (h(1, 0) - h(-1, 0)) / 2.0;
+            let q = (h(0, 1) - h(0, -1)) / 2.0;
+            let r = h(1, 0) - 2.0 * h(0, 0) + h(-1, 0);
+            let t = h(0, 1) - 2.0 * h(0, 0) + h(0, -1);
+            let s = (h(1, 1) - h(-1, 1) - h(1, -1) + h(-1, -1)) / 4.0;
+            // the direction of a flat pixel is undefined, so it has no curvature
+            let value = if p == 0.0 && q == 0.0 { 0.0 } else { formula(p, q, r, s, t) };
+            strongest = strongest.max(value.abs());
+            values.push(value);
+        }
+    }
+    let mut buffer: Buf = image::ImageBuffer::new(input.width(), input.height());
+    for (pixel, value) in buffer.pixels_mut().zip(values) {
+        let scaled = if strongest == 0.0 { 0.0 } else { value / strongest * 127.0 };
+        let color = (128.0 + scaled).round().clamp(0.0, 255.0) as u8;
+        *pixel = image::Rgb([color, color, color]);
+    }
+    buffer
+}
@@ -0,0 +1,231 @@
+//! Tracing maps into polylines for vector output.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::error::require;
+use crate::{Buf, Result};
+
+/// Polyline in image space, a pixel (x, y) covers x..x+1 and y..y+1.
+/// Closed loops end on the same point they started on.
+pub type Path = Vec<(f32, f32)>;
+
+// two endpoint keys that chain_segments joins on
+type Segment = ((u32, u32), (u32, u32));
+
+/// Traces the isoline where the red channel crosses `level` using marching squares.
+/// Anything at or above `level` counts as inside.
+pub fn contour_lines(input: &Buf, level: f32) -> Vec<Path> {
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut positions: HashMap<(u32, u32), (f32, f32)> = HashMap::new();
+    if input.width() < 2 || input.height() < 2 {
+        return Vec::new();
+    }
+    let sample = |x: u32, y: u32| input.get_pixel(x, y)[0] as f32;
+    for y in 0..input.height() - 1 {
+        for x in 0..input.width() - 1 {
+            let top_left = sample(x, y);
+            let top_right = sample(x + 1, y);
+            let bottom_right = sample(x + 1, y + 1);
+            let bottom_left = sample(x, y + 1);
+            let case = ((top_left >= level) as u8) << 3
+                | ((top_right >= level) as u8) << 2
+                | ((bottom_right >= level) as u8) << 1
+                | (bottom_left >= level) as u8;
+            if case == 0 || case == 15 {
+                continue;
+            }
+            // edge crossings are keyed on a doubled grid so neighbouring cells share them
+            let crossing = |a: f32, b: f32| if a == b { 0.5 } else { (level - a) / (b - a) };
+            let top = (2 * x + 1, 2 * y);
+            let bottom = (2 * x + 1, 2 * y + 2);
+            let left = (2 * x, 2 * y + 1);
+            let right = (2 * x + 2, 2 * y + 1);
+            let center_x = x as f32 + 0.5;
+            let center_y = y as f32 + 0.5;
+            positions.insert(top, (center_x + crossing(top_left, top_right), center_y));
+            positions.insert(bottom, (center_x + crossing(bottom_left, bottom_right), center_y + 1.0));
+            positions.insert(left, (center_x, center_y + crossing(top_left, bottom_left)));
+            positions.insert(right, (center_x + 1.0, center_y + crossing(top_right, bottom_right)));
+            // saddles are resolved with the average of the four corners
+            let center_inside = (top_left + top_right + bottom_right + bottom_left) / 4.0 >= level;
+            match case {
+                1 | 14 => segments.push((left, bottom)),
+                2 | 13 => segments.push((bottom, right)),
+                3 | 12 => segments.push((left, right)),
+                4 | 11 => segments.push((top, right)),
+                6 | 9 => segments.push((top, bottom)),
+                7 | 8 => segments.push((left, top)),
+                5 => {
+                    if center_inside {
+                        segments.push((left, top));
+                        segments.push((bottom, right));
+                    } else {
+                        segments.push((top, right));
+                        segments.push((left, bottom));
+                    }
+                }
+                10 => {
+                    if center_inside {
+                        segments.push((top, right));
+                        segments.push((left, bottom));
+                    } else {
+                        segments.push((left, top));
+                        segments.push((bottom, right));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    chain_segments(&segments, &positions)
+}
+
+/// Traces the pixel edges between differently labelled pixels, `labels` is row by row
+/// like [`voronoi_labels`](crate::noise::voronoi_labels) returns.
+pub fn region_borders(labels: &[u32], width: u32, height: u32) -> Result<Vec<Path>> {
+    require(labels.len() == width as usize * height as usize, || format!("{} labels don't cover a {width}x{height} image", labels.len()))?;
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut positions: HashMap<(u32, u32), (f32, f32)> = HashMap::new();
+    let label = |x: u32, y: u32| labels[(y * width + x) as usize];
+    for y in 0..height {
+        for x in 0..width {
+            if x + 1 < width && label(x, y) != label(x + 1, y) {
+                segments.push(((x + 1, y), (x + 1, y + 1)));
+            }
+            if y + 1 < height && label(x, y) != label(x, y + 1) {
+                segments.push(((x, y + 1), (x + 1, y + 1)));
+            }
+        }
+    }
+    for &(start, end) in &segments {
+        positions.insert(start, (start.0 as f32, start.1 as f32));
+        positions.insert(end, (end.0 as f32, end.1 as f32));
+    }
+    Ok(chain_segments(&segments, &positions))
+}
+
+// joins loose segments that share endpoints into the longest polylines it can
+// closed loops end on the same point they started on
+fn chain_segments(segments: &[Segment], positions: &HashMap<(u32, u32), (f32, f32)>) -> Vec<Path> {
+    let mut touching: HashMap<(u32, u32), Vec<usize>> = HashMap::new();
+    for (i, &(start, end)) in segments.iter().enumerate() {
+        touching.entry(start).or_default().push(i);
+        touching.entry(end).or_default().push(i);
+    }
+    let mut used: Vec<bool> = vec![false; segments.len()];
+    let mut paths: Vec<Path> = Vec::new();
+    for i in 0..segments.len() {
+        if used[i] {
+            continue;
+        }
+        used[i] = true;
+        let mut keys: VecDeque<(u32, u32)> = VecDeque::from([segments[i].0, segments[i].1]);
+        // walk forward from the end, then backward from the start
+        for forward in [true, false] {
+            loop {
+                let current = if forward { *keys.back().unwrap() } else { *keys.front().unwrap() };
+                let next = touching[&current].iter().copied().find(|&k| !used[k]);
+                let Some(k) = next else { break };
+                used[k] = true;
+                let other = if segments[k].0 == current { segments[k].1 } else { segments[k].0 };
+                if forward {
+                    keys.push_back(other);
+                } else {
+                    keys.push_front(other);
+                }
+            }
+        }
+        paths.push(keys.iter().map(|key| positions[key]).collect());
+    }
+    paths
+}
+
+fn point_line_distance(point: (f32, f32), start: (f32, f32), end: (f32, f32)) -> f32 {
+    let dx = end.0 - start.0;
+    let dy = end.1 - start.1;
+    let length = (dx * dx + dy * dy).sqrt();
+    if length == 0.0 {
+        return ((point.0 - start.0).powi(2) + (point.1 - start.1).powi(2)).sqrt();
+    }
+    (dy * point.0 - dx * point.1 + end.0 * start.1 - end.1 * start.0).abs() / length
+}
+
+/// Ramer-Douglas-Peucker, drops points that are closer than `tolerance` pixels to the simplified line.
+pub fn simplify_path(path: &[(f32, f32)], tolerance: f32) -> Path {
+    if path.len() < 3 {
+        return path.to_vec();
+    }
+    // closed loops are split at the point furthest from the start so both halves have a real baseline
+    if path[0] == path[path.len() - 1] {
+        let start = path[0];
+        let (furthest, _) = path.iter().enumerate().fold((0, 0.0), |best, (i, point)| {
+            let dist = (point.0 - start.0).powi(2) + (point.1 - start.1).powi(2);
+            if dist > best.1 { (i, dist) } else { best }
+        });
+        if furthest != 0 {
+            let mut simplified: Path = simplify_open_path(&path[..=furthest], tolerance);
+            simplified.pop();
+            simplified.extend(simplify_open_path(&path[furthest..], tolerance));
+            return simplified;
+        }
+    }
+    simplify_open_path(path, tolerance)
+}
+
+fn simplify_open_path(path: &[(f32, f32)], tolerance: f32) -> Path {
+    if path.len() < 3 {
+        return path.to_vec();
+    }
+    let mut keep: Vec<bool> = vec![false; path.len()];
+    keep[0] = true;
+    keep[path.len() - 1] = true;
+    let mut stack: Vec<(usize, usize)> = vec![(0, path.len() - 1)];
+    while let Some((start, end)) = stack.pop() {
+        let mut max_dist: f32 = 0.0;
+        let mut index: usize = start;
+        for (i, &point) in path.iter().enumerate().take(end).skip(start + 1) {
+            let dist = point_line_distance(point, path[start], path[end]);
+            if dist > max_dist {
+                max_dist = dist;
+                index = i;
+            }
+        }
+        if max_dist > tolerance {
+            keep[index] = true;
+            stack.push((start, index));
+            stack.push((index, end));
+        }
+    }
+    path.iter().zip(keep).filter(|(_, kept)| *kept).map(|(point, _)| *point).collect()
+}
+
+/// Simplifies every path and drops the ones that collapse into specks.
+pub fn simplify_paths(paths: Vec<Path>, tolerance: f32) -> Vec<Path> {
+    paths.iter().map(|path| simplify_path(path, tolerance)).filter(|path| {
+        let closed = path.len() > 2 && path[0] == path[path.len() - 1];
+        if closed { path.len() >= 4 } else { path.len() >= 2 }
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contour_lines_close_around_a_disk() {
+        let disk: Buf = image::ImageBuffer::from_fn(21, 21, |x, y| {
+            let inside = (x as i32 - 10).pow(2) + (y as i32 - 10).pow(2) <= 25;
+            image::Rgb(if inside { [255; 3] } else { [0; 3] })
+        });
+        let paths: Vec<Path> = contour_lines(&disk, 127.5);
+        assert_eq!(paths.len(), 1);
+        let path = &paths[0];
+        assert!(path.len() > 8);
+        assert_eq!(path.first(), path.last());
+        for &(x, y) in path {
+            // pixel centers sit at .5, so the disk's center is at 10.5, 10.5
+            let radius = ((x - 10.5).powi(2) + (y - 10.5).powi(2)).sqrt();
+            assert!((4.5..=6.0).contains(&radius), "{x}, {y} is {radius} from the center");
+        }
+    }
+}
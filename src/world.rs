@@ -0,0 +1,122 @@
+//! Endless worlds generated a chunk at a time.
+//!
+//! The noise here isn't drawn from a random generator like [`noise`](crate::noise), every lattice value is a
+//! hash of the seed and its global coordinates. Any part of the world can be generated on its own and
+//! always comes out the same, so chunks made separately line up exactly when placed next to each other.
+
+use crate::error::require;
+use crate::planet::planet_height;
+use crate::{Field, Result};
+
+/// Fractal value noise over an endless plane, in world pixels.
+#[derive(Clone, Copy)]
+pub struct WorldNoise {
+    /// Every value of the world comes from this.
+    pub seed: u64,
+    /// World pixels per lattice cell in the first octave.
+    pub cell_size: f64,
+    /// How many times smaller the cells of each octave are.
+    pub inc: u32,
+    /// Number of octaves.
+    pub octaves: u8,
+}
+
+// splitmix64 finalizer, spreads every input bit over the whole hash
+pub(crate) fn mix(mut hash: u64) -> u64 {
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d049bb133111eb);
+    hash ^ (hash >> 31)
+}
+
+// top 24 bits of a hash as 0.0..1.0
+pub(crate) fn hash_to_unit(hash: u64) -> f32 {
+    (hash >> 40) as f32 / (1u64 << 24) as f32
+}
+
+// seed and octave part of a lattice hash, coordinates get xored in on top
+pub(crate) fn octave_key(seed: u64, octave: u8) -> u64 {
+    seed ^ (octave as u64).wrapping_mul(0xd6e8feb86659fd93)
+}
+
+/// The random 0.0..1.0 value of lattice point (`x`, `y`) in one octave of the world.
+pub fn lattice_value(seed: u64, octave: u8, x: i64, y: i64) -> f32 {
+    hash_to_unit(mix(octave_key(seed, octave) ^ (x as u64).wrapping_mul(0x9e3779b97f4a7c15) ^ (y as u64).wrapping_mul(0xc2b2ae3d27d4eb4f)))
+}
+
+// bilinear value noise of one octave at a world position in lattice cells
+fn octave_value(seed: u64, octave: u8, x: f64, y: f64) -> f32 {
+    let (cell_x, cell_y) = (x.floor(), y.floor());
+    let (iter_x, iter_y) = (cell_x as i64, cell_y as i64);
+    let (across, down) = ((x - cell_x) as f32, (y - cell_y) as f32);
+    let top = lattice_value(seed, octave, iter_x, iter_y) * (1.0 - across) + lattice_value(seed, octave, iter_x + 1, iter_y) * across;
+    let bottom = lattice_value(seed, octave, iter_x, iter_y + 1) * (1.0 - across) + lattice_value(seed, octave, iter_x + 1, iter_y + 1) * across;
+    top * (1.0 - down) + bottom * down
+}
+
+/// Height of the world at (`x`, `y`) world pixels, 0.0..=1.0.
+///
+/// Octaves are weighted like [`fractal_value_field`](crate::noise::fractal_value_field) but divided by
+/// the total weight instead of capped, a chunk can't be stretched to fit on its own without breaking its seams.
+pub fn world_height(noise: &WorldNoise, x: f64, y: f64) -> f32 {
+    let mut cell_size: f64 = noise.cell_size;
+    let mut height: f32 = octave_value(noise.seed, 0, x / cell_size, y / cell_size);
+    let mut total: f32 = 1.0;
+    let mut level: f32 = 0.25;
+    for octave in 1..noise.octaves.max(1) {
+        cell_size /= noise.inc as f64;
+        height += octave_value(noise.seed, octave, x / cell_size, y / cell_size) * level;
+        total += level;
+        level /= 2.0;
+    }
+    height / total
+}
+
+/// Samples a `width` x `height` window of the world with its top left pixel at (`x`, `y`),
+/// each pixel covering `pixel_size` world pixels.
+pub fn world_field(noise: &WorldNoise, x: f64, y: f64, width: u32, height: u32, pixel_size: f64) -> Result<Field> {
+    require(noise.cell_size > 0.0, || format!("world cells need a size above 0, got {}", noise.cell_size))?;
+    require(noise.inc >= 1, || "world octaves need an inc of at least 1".to_string())?;
+    require(pixel_size > 0.0, || format!("world pixels need a size above 0, got {pixel_size}"))?;
+    let mut buffer: Field = image::ImageBuffer::new(width, height);
+    for (pixel_x, pixel_y, pixel) in buffer.enumerate_pixels_mut() {
+        let world_x = x + pixel_x as f64 * pixel_size;
+        let world_y = y + pixel_y as f64 * pixel_size;
+        *pixel = image::Luma([world_height(noise, world_x, world_y)]);
+    }
+    Ok(buffer)
+}
+
+/// Chunk (`chunk_x`, `chunk_y`) of the world, `chunk_size` pixels square. Chunk (0, 0) starts at the world
+/// origin and chunk (1, 0) carries on from the pixel right of it, so chunks tile into one continuous map.
+pub fn chunk_field(noise: &WorldNoise, chunk_x: i64, chunk_y: i64, chunk_size: u32) -> Result<Field> {
+    require(chunk_size >= 1, || "chunks need to be at least 1 pixel".to_string())?;
+    let x = chunk_x as f64 * chunk_size as f64;
+    let y = chunk_y as f64 * chunk_size as f64;
+    world_field(noise, x, y, chunk_size, chunk_size, 1.0)
+}
+
+/// Tile (`x`, `y`) of zoom level `zoom` in a slippy map pyramid over the world square from the origin to
+/// (`world_size`, `world_size`). Zoom 0 is that square in a single tile and every level doubles the tiles per side.
+///
+/// Deeper zooms get extra octaves so the finest cells stay about as many pixels wide as at zoom 0,
+/// zooming in shows new detail instead of blurry lattice cells.
+pub fn tile_field(noise: &WorldNoise, world_size: f64, zoom: u8, x: u32, y: u32, tile_size: u32) -> Result<Field> {
+    require(zoom < 32, || format!("zoom levels go up to 31, got {zoom}"))?;
+    require(x < 1 << zoom && y < 1 << zoom, || format!("zoom {zoom} has no tile {x}, {y}"))?;
+    let extra_octaves: f64 = if noise.inc > 1 { (zoom as f64 * 2f64.ln() / (noise.inc as f64).ln()).ceil() } else { 0.0 };
+    let deeper = WorldNoise { octaves: noise.octaves.saturating_add(extra_octaves as u8), ..*noise };
+    let tile_world_size: f64 = world_size / (1u64 << zoom) as f64;
+    world_field(&deeper, x as f64 * tile_world_size, y as f64 * tile_world_size, tile_size, tile_size, tile_world_size / tile_size as f64)
+}
+
+/// A `width` x `height` window from the origin of 3D value noise cut at depth `time`, everything in world pixels.
+/// Nearby times give similar slices, so stepping through time morphs the map smoothly instead of panning it.
+pub fn time_slice_field(noise: &WorldNoise, width: u32, height: u32, time: f64) -> Result<Field> {
+    require(noise.cell_size > 0.0, || format!("world cells need a size above 0, got {}", noise.cell_size))?;
+    require(noise.inc >= 1, || "world octaves need an inc of at least 1".to_string())?;
+    let mut buffer: Field = image::ImageBuffer::new(width, height);
+    for (x, y, pixel) in buffer.enumerate_pixels_mut() {
+        *pixel = image::Luma([planet_height(noise, [x as f64, y as f64, time])]);
+    }
+    Ok(buffer)
+}